@@ -0,0 +1,73 @@
+use crate::{
+    taskfile::{Bundle, Taskfile},
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::{value_parser, Arg, ArgMatches};
+use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("build-cli")
+        .about("build a standalone CLI that carries the Taskfile, named and versioned after it")
+        .arg(
+            Arg::new("name")
+                .long("name")
+                .required(true)
+                .help("the name of the CLI, used in its help output"),
+        )
+        .arg(
+            Arg::new("out")
+                .long("out")
+                .short('o')
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(clap::ValueHint::FilePath)
+                .help("where to write the binary, `dist/<name>` when left out"),
+        );
+}
+
+fn build_error(message: String) -> UserFacingError {
+    return UserFacingError::TaskExecutionError(ErrWithMessage {
+        code: "BUILD_ERROR".to_string(),
+        messages: vec![message],
+    });
+}
+
+pub fn run(matches: &ArgMatches, taskfile: &Taskfile) -> Result<(), UserFacingError> {
+    let name = matches.get_one::<String>("name").unwrap();
+    if name.is_empty() || name.contains('/') {
+        return Err(build_error(format!("`{}` is not a valid CLI name", name)));
+    }
+    let out = match matches.get_one::<PathBuf>("out") {
+        Some(out) => out.to_owned(),
+        None => PathBuf::from("dist").join(name),
+    };
+    // a built CLI can be renamed, its Taskfile is not on disk to bundle again
+    let bundle = match (Bundle::embedded(), &taskfile.path) {
+        (Some(embedded), _) => Bundle {
+            name: name.to_owned(),
+            taskfile: embedded.taskfile,
+        },
+        (None, Some(path)) => Bundle::new(name, path)?,
+        (None, None) => {
+            return Err(build_error(
+                "the Taskfile was not loaded from a file".to_string(),
+            ))
+        }
+    };
+    let binary = std::env::current_exe()
+        .map_err(|e| build_error(format!("could not find the tasker binary: {}", e)))?;
+    let output = bundle.attach(&binary)?;
+    let written = match out.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => std::fs::create_dir_all(dir),
+        _ => Ok(()),
+    }
+    .and_then(|_| std::fs::write(&out, output))
+    .and_then(|_| std::fs::set_permissions(&out, std::fs::Permissions::from_mode(0o755)));
+    written.map_err(|e| {
+        UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "WRITE_ERROR".to_string(),
+            messages: vec![format!("could not write `{}`: {}", out.display(), e)],
+        })
+    })?;
+    println!("CLI written to {}", out.display());
+    return Ok(());
+}
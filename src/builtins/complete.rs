@@ -0,0 +1,347 @@
+use super::Builtin;
+use crate::{cliargs::CliArgs, taskfile::Taskfile, utils::errors::UserFacingError};
+use clap::{Arg, ArgMatches, CommandFactory, ValueHint};
+use std::path::Path;
+
+// called by the scripts from `tasker completions`, not meant to be typed
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("__complete")
+        .hide(true)
+        .disable_help_flag(true)
+        .arg(
+            Arg::new("words")
+                .num_args(0..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true),
+        );
+}
+
+fn option<'a>(command: &'a clap::Command, word: &str) -> Option<&'a Arg> {
+    let name = word.split('=').next().unwrap_or(word);
+    return command
+        .get_arguments()
+        .find(|arg| match name.strip_prefix("--") {
+            Some(long) => arg.get_long() == Some(long),
+            None => name.len() == 2 && arg.get_short() == name.chars().nth(1),
+        });
+}
+fn takes_value(arg: &Arg, word: &str) -> bool {
+    return arg.get_action().takes_values() && !arg.is_positional() && !word.contains('=');
+}
+
+fn with_prefix(current: &str, candidates: Vec<String>) -> Vec<String> {
+    return candidates
+        .into_iter()
+        .filter(|candidate| candidate.starts_with(current))
+        .collect();
+}
+
+// files and directories starting with `current`, directories end in `/`
+fn paths(current: &str) -> Vec<String> {
+    let (dir, prefix) = match current.rfind('/') {
+        Some(i) => (&current[..=i], &current[i + 1..]),
+        None => ("", current),
+    };
+    let read_from = match dir.is_empty() {
+        true => ".".to_string(),
+        false => shellexpand::tilde(dir).to_string(),
+    };
+    let entries = match std::fs::read_dir(read_from) {
+        Ok(entries) => entries,
+        Err(_) => return vec![],
+    };
+    let mut paths: Vec<String> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // hidden files only when asked for
+            if !name.starts_with(prefix) || (name.starts_with('.') && !prefix.starts_with('.')) {
+                return None;
+            }
+            return match entry.path().is_dir() {
+                true => Some(format!("{}{}/", dir, name)),
+                false => Some(format!("{}{}", dir, name)),
+            };
+        })
+        .collect();
+    paths.sort();
+    return paths;
+}
+
+fn task_names(taskfile: Option<&Taskfile>) -> Vec<String> {
+    let mut names: Vec<String> = match taskfile {
+        Some(taskfile) => taskfile
+            .tasks
+            .iter()
+            .map(|task| task.name.to_owned())
+            .collect(),
+        None => vec![],
+    };
+    for builtin in Builtin::ALL {
+        let shadowed =
+            taskfile.is_some_and(|taskfile| taskfile.get_task_by_name(builtin.name()).is_some());
+        if !shadowed && !builtin.clap_command().is_hide_set() {
+            names.push(builtin.name().to_string());
+        }
+    }
+    return names;
+}
+
+// values for an option or positional of tasker itself or of a builtin
+fn values(arg: &Arg, current: &str, taskfile: Option<&Taskfile>) -> Vec<String> {
+    if arg.get_id() == "context" {
+        // `-x` takes a comma separated list
+        let (done, last) = match current.rfind(',') {
+            Some(i) => (&current[..=i], &current[i + 1..]),
+            None => ("", current),
+        };
+        let names = match taskfile {
+            Some(taskfile) => taskfile.context_names(),
+            None => vec![],
+        };
+        let names = names
+            .into_iter()
+            .map(|name| format!("{}{}", done, name))
+            .collect();
+        return with_prefix(&format!("{}{}", done, last), names);
+    }
+    if arg.get_id() == "task" {
+        return with_prefix(current, task_names(taskfile));
+    }
+    let possible: Vec<String> = arg
+        .get_possible_values()
+        .iter()
+        .filter(|value| !value.is_hide_set())
+        .map(|value| value.get_name().to_string())
+        .collect();
+    if !possible.is_empty() {
+        return with_prefix(current, possible);
+    }
+    if matches!(
+        arg.get_value_hint(),
+        ValueHint::AnyPath | ValueHint::FilePath | ValueHint::DirPath
+    ) {
+        return paths(current);
+    }
+    return vec![];
+}
+
+fn options(command: &clap::Command, current: &str, taskfile: Option<&Taskfile>) -> Vec<String> {
+    // `--format=js` completes the value after the `=`
+    if let Some((name, value)) = current.split_once('=') {
+        return match option(command, name) {
+            Some(arg) => values(arg, value, taskfile)
+                .into_iter()
+                .map(|value| format!("{}={}", name, value))
+                .collect(),
+            None => vec![],
+        };
+    }
+    let mut names = vec![];
+    for arg in command.get_arguments().filter(|arg| !arg.is_hide_set()) {
+        names.extend(arg.get_long().map(|long| format!("--{}", long)));
+        if current.len() < 2 {
+            names.extend(arg.get_short().map(|short| format!("-{}", short)));
+        }
+    }
+    return with_prefix(current, names);
+}
+
+// args of a task from the Taskfile, they are all positional
+fn task_args(taskfile: &Taskfile, name: &str, words: &[String], current: &str) -> Vec<String> {
+    let task = match taskfile.get_task_by_name(name) {
+        Some(task) => task,
+        None => return vec![],
+    };
+    if current.starts_with('-') {
+        return with_prefix(current, vec!["--help".to_string()]);
+    }
+    let position = words.iter().filter(|word| !word.starts_with('-')).count();
+    let arg = match task.get_command_args().get(position) {
+        Some(arg) => arg,
+        None => return vec![],
+    };
+    if !arg.choices.is_empty() {
+        return with_prefix(current, arg.choices.to_owned());
+    }
+    return match arg.arg_type.to_lowercase().as_str() {
+        "path" | "file" | "dir" => paths(current),
+        "bool" | "boolean" => with_prefix(current, vec!["true".to_string(), "false".to_string()]),
+        _ => vec![],
+    };
+}
+
+// options and positionals of a builtin, `words` are the ones after its name
+fn builtin_args(
+    builtin: Builtin,
+    words: &[String],
+    current: &str,
+    taskfile: Option<&Taskfile>,
+) -> Vec<String> {
+    let mut command = builtin.clap_command();
+    command.build();
+    let mut positionals = vec![];
+    let mut index = 0;
+    while index < words.len() {
+        let word = &words[index];
+        match option(&command, word) {
+            Some(arg) if takes_value(arg, word) => {
+                if index + 1 == words.len() {
+                    return values(arg, current, taskfile);
+                }
+                index += 1;
+            }
+            Some(_) => {}
+            None => positionals.push(word.to_owned()),
+        }
+        index += 1;
+    }
+    if current.starts_with('-') {
+        return options(&command, current, taskfile);
+    }
+    let args: Vec<&Arg> = command.get_positionals().collect();
+    let arg = match args.get(positionals.len()).or(args.last()) {
+        Some(arg) => *arg,
+        None => return vec![],
+    };
+    // `tasker explain <task> <args>` takes the args of the task
+    if arg.get_id() == "args" {
+        if let (Some(taskfile), Some(task)) = (taskfile, positionals.first()) {
+            return task_args(taskfile, task, &positionals[1..], current);
+        }
+    }
+    return values(arg, current, taskfile);
+}
+
+// what can go in place of the last of `words`, the ones typed after `tasker`
+pub fn candidates(taskfile: Option<&Taskfile>, words: &[String]) -> Vec<String> {
+    let (current, before) = match words.split_last() {
+        Some((current, before)) => (current.as_str(), before),
+        None => ("", words),
+    };
+    let mut global = CliArgs::command();
+    global.build();
+    let mut index = 0;
+    while index < before.len() {
+        let word = &before[index];
+        if !word.starts_with('-') || word == "-" {
+            break;
+        }
+        if let Some(arg) = option(&global, word) {
+            if takes_value(arg, word) {
+                if index + 1 == before.len() {
+                    return values(arg, current, taskfile);
+                }
+                index += 1;
+            }
+        }
+        index += 1;
+    }
+    if index >= before.len() {
+        if current.starts_with('-') {
+            return options(&global, current, taskfile);
+        }
+        return with_prefix(current, task_names(taskfile));
+    }
+    let name = &before[index];
+    let rest = &before[index + 1..];
+    let task = taskfile.and_then(|taskfile| taskfile.get_task_by_name(name));
+    return match (
+        task,
+        Builtin::ALL
+            .into_iter()
+            .find(|builtin| builtin.name() == name),
+    ) {
+        (Some(_), _) => task_args(taskfile.unwrap(), name, rest, current),
+        (None, Some(builtin)) => builtin_args(builtin, rest, current, taskfile),
+        (None, None) => vec![],
+    };
+}
+
+// the Taskfile named with `-c` on the line being completed, if it is not the default one
+fn config_from_words(words: &[String]) -> Option<Taskfile> {
+    let index = words
+        .iter()
+        .take_while(|word| word.starts_with('-'))
+        .position(|word| word == "-c" || word == "--config")?;
+    let path = shellexpand::tilde(words.get(index + 1)?).to_string();
+    if !Path::new(&path).is_file() {
+        return None;
+    }
+    return Taskfile::new(path).ok();
+}
+
+pub fn run(matches: &ArgMatches, taskfile: Option<&Taskfile>) -> Result<(), UserFacingError> {
+    let words: Vec<String> = match matches.get_many::<String>("words") {
+        Some(words) => words.cloned().collect(),
+        None => vec![],
+    };
+    let from_words = config_from_words(&words);
+    for candidate in candidates(from_words.as_ref().or(taskfile), &words) {
+        println!("{}", candidate);
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::candidates;
+    use crate::{taskfile::Taskfile, utils::test_helpers::test_helpers::load_from_string};
+
+    fn complete(line: &str) -> Vec<String> {
+        let taskfile = load_from_string();
+        let mut words: Vec<String> = line.split_whitespace().map(String::from).collect();
+        if line.is_empty() || line.ends_with(' ') {
+            words.push(String::new());
+        }
+        return candidates(Some(&taskfile), &words);
+    }
+
+    #[test]
+    fn test_complete_task_names() {
+        assert_eq!(
+            complete("test-s"),
+            vec!["test-shells".to_string(), "test-sh".to_string()]
+        );
+        let all = complete("");
+        assert!(all.contains(&"test-cmd".to_string()));
+        assert!(all.contains(&"list".to_string()));
+        assert!(!all.contains(&"__complete".to_string()));
+        assert_eq!(complete("-d -x test test-c").len(), 2);
+    }
+    #[test]
+    fn test_complete_options() {
+        assert_eq!(complete("--dr"), vec!["--dry-run"]);
+        assert_eq!(complete("-x "), vec!["test", "test-child"]);
+        assert_eq!(complete("-x test,test-"), vec!["test,test-child"]);
+        assert_eq!(complete("--context=test-"), vec!["--context=test-child"]);
+        assert_eq!(complete("-c src/tests/Task"), vec!["src/tests/Taskfile"]);
+        assert_eq!(complete("-c sr"), vec!["src/"]);
+    }
+    #[test]
+    fn test_complete_builtin_args() {
+        assert_eq!(complete("list --format y"), vec!["yaml"]);
+        assert_eq!(complete("list --f"), vec!["--format"]);
+        assert_eq!(complete("graph test-cl"), vec!["test-cleanup"]);
+        assert_eq!(complete("completions f"), vec!["fish"]);
+    }
+    #[test]
+    fn test_complete_task_args() {
+        let yaml = r#"
+        tasks:
+          - name: test-cmd
+            commands: []
+            args:
+              - {name: env, type: string, choices: [staging, prod]}
+              - {name: out, type: path}
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let words = ["test-cmd".to_string(), "st".to_string()];
+        assert_eq!(candidates(Some(&taskfile), &words), vec!["staging"]);
+        let words = ["explain".to_string(), "test-cmd".to_string(), String::new()];
+        assert_eq!(candidates(Some(&taskfile), &words), vec!["staging", "prod"]);
+        let words = ["test-cmd".to_string(), "prod".to_string(), "sr".to_string()];
+        assert_eq!(candidates(Some(&taskfile), &words), vec!["src/"]);
+        assert_eq!(complete("test-cmd --"), vec!["--help"]);
+    }
+}
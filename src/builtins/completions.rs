@@ -0,0 +1,180 @@
+use crate::utils::errors::UserFacingError;
+use clap::{value_parser, Arg, ArgMatches, ValueEnum};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum CompletionShell {
+    Bash,
+    Zsh,
+    Fish,
+    Elvish,
+    Powershell,
+}
+
+// every script asks `tasker __complete` for candidates, so tasks, contexts and arg values
+// come from whatever Taskfile is around when tab is pressed. `{program}` is the name of the
+// CLI quoted for the shell, `{name}` the bare name and `{function}` a function named after it
+const BASH: &str = r#"{function}() {
+    local line="${COMP_LINE:0:COMP_POINT}"
+    local -a words
+    read -ra words <<< "$line"
+    [[ $line =~ [[:space:]]$ ]] && words+=("")
+    local cur="${words[${#words[@]}-1]}"
+    local IFS=$'\n'
+    COMPREPLY=($({program} __complete "${words[@]:1}" 2>/dev/null))
+    # bash splits words on `:`, so only the part after the last one gets replaced
+    if [[ $cur == *:* && $COMP_WORDBREAKS == *:* ]]; then
+        local colon_prefix="${cur%"${cur##*:}"}"
+        local i
+        for i in "${!COMPREPLY[@]}"; do
+            COMPREPLY[$i]="${COMPREPLY[$i]#"$colon_prefix"}"
+        done
+    fi
+    if [[ ${#COMPREPLY[@]} -eq 1 && ${COMPREPLY[0]} == */ ]]; then
+        compopt -o nospace
+    fi
+}
+complete -F {function} {program}
+"#;
+
+const ZSH: &str = r#"#compdef {name}
+{function}() {
+    local -a candidates dirs others
+    candidates=("${(@f)$({program} __complete "${(@)words[2,CURRENT]}" 2>/dev/null)}")
+    candidates=(${candidates:#})
+    dirs=(${(M)candidates:#*/})
+    others=(${candidates:#*/})
+    (( ${#dirs} )) && compadd -S '' -- "${dirs[@]}"
+    (( ${#others} )) && compadd -- "${others[@]}"
+}
+compdef {function} {program}
+"#;
+
+const FISH: &str = r#"function {function}
+    set -l words (commandline -opc)
+    {program} __complete $words[2..-1] (commandline -ct) 2>/dev/null
+end
+complete -c {program} -f -a '({function})'
+"#;
+
+const ELVISH: &str = r#"use str
+set edit:completion:arg-completer[{program}] = {|@words|
+    try {
+        {program} __complete (all $words[1..]) 2>/dev/null | from-lines | each {|candidate|
+            if (str:has-suffix $candidate /) {
+                edit:complex-candidate $candidate &code-suffix=''
+            } else {
+                put $candidate
+            }
+        }
+    } catch { }
+}
+"#;
+
+const POWERSHELL: &str = r#"Register-ArgumentCompleter -Native -CommandName {program} -ScriptBlock {
+    param($wordToComplete, $commandAst, $cursorPosition)
+    $words = @($commandAst.CommandElements |
+        Select-Object -Skip 1 |
+        Where-Object { $_.Extent.EndOffset -le $cursorPosition } |
+        ForEach-Object { $_.ToString() })
+    if ($wordToComplete -eq '') { $words += '' }
+    & {program} __complete @words 2>$null | ForEach-Object {
+        [System.Management.Automation.CompletionResult]::new($_, $_, 'ParameterValue', $_)
+    }
+}
+"#;
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("completions")
+        .about("print a shell completion script that completes tasks, contexts and args")
+        .arg(
+            Arg::new("shell")
+                .required(true)
+                .value_parser(value_parser!(CompletionShell))
+                .help("shell to complete in"),
+        );
+}
+
+// `program` as a word of `shell`, quoted when it is more than letters, digits and `-_.+`
+fn quote(shell: CompletionShell, program: &str) -> String {
+    let plain = !program.is_empty()
+        && program
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.+".contains(c));
+    if plain {
+        return program.to_string();
+    }
+    return match shell {
+        CompletionShell::Bash | CompletionShell::Zsh => {
+            format!("'{}'", program.replace('\'', "'\\''"))
+        }
+        CompletionShell::Fish => {
+            format!("'{}'", program.replace('\\', "\\\\").replace('\'', "\\'"))
+        }
+        CompletionShell::Elvish | CompletionShell::Powershell => {
+            format!("'{}'", program.replace('\'', "''"))
+        }
+    };
+}
+
+// `program` is the name the CLI runs under, a symlinked or built CLI completes itself
+pub fn script(shell: CompletionShell, program: &str) -> String {
+    let script = match shell {
+        CompletionShell::Bash => BASH,
+        CompletionShell::Zsh => ZSH,
+        CompletionShell::Fish => FISH,
+        CompletionShell::Elvish => ELVISH,
+        CompletionShell::Powershell => POWERSHELL,
+    };
+    let function: String = program
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    let function = match shell {
+        CompletionShell::Fish => format!("__{}_complete", function),
+        _ => format!("_{}", function),
+    };
+    return script
+        .replace("{function}", &function)
+        .replace("{program}", &quote(shell, program))
+        .replace("{name}", program);
+}
+
+pub fn run(matches: &ArgMatches, program: &str) -> Result<(), UserFacingError> {
+    let shell = *matches.get_one::<CompletionShell>("shell").unwrap();
+    print!("{}", script(shell, program));
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{script, CompletionShell};
+    use clap::ValueEnum;
+
+    #[test]
+    fn test_scripts_call_back_into_tasker() {
+        for shell in CompletionShell::value_variants() {
+            assert!(script(*shell, "tasker").contains("tasker __complete"));
+            let script = script(*shell, "deployctl");
+            assert!(script.contains("deployctl __complete"));
+            assert!(!script.contains("tasker"));
+        }
+    }
+    #[test]
+    fn test_scripts_quote_the_name() {
+        let bash = script(CompletionShell::Bash, "my tasks");
+        assert!(bash.contains("COMPREPLY=($('my tasks' __complete"));
+        assert!(bash.contains("complete -F _my_tasks 'my tasks'\n"));
+        let fish = script(CompletionShell::Fish, "it's");
+        assert!(fish.contains("'it\\'s' __complete"));
+        assert!(fish.contains("complete -c 'it\\'s' -f -a '(__it_s_complete)'"));
+        let powershell = script(CompletionShell::Powershell, "it's");
+        assert!(powershell.contains("& 'it''s' __complete"));
+        // only the name slots change, not words that happen to contain the name
+        let zsh = script(CompletionShell::Zsh, "c");
+        assert!(zsh.starts_with("#compdef c\n_c() {\n"));
+        assert!(zsh.contains("local -a candidates dirs others"));
+    }
+}
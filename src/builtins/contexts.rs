@@ -0,0 +1,49 @@
+use crate::{taskfile::Taskfile, utils::errors::UserFacingError};
+use clap::ArgMatches;
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("contexts")
+        .about("list the contexts of the Taskfile with their resolved values");
+}
+
+// one block per context, values include everything it extends
+pub fn render(taskfile: &Taskfile) -> Result<String, UserFacingError> {
+    let mut output = String::new();
+    for (name, values) in taskfile.resolved_contexts()? {
+        let extends = &taskfile.contexts[&name].extends;
+        match extends.is_empty() {
+            true => output.push_str(&format!("{}\n", name)),
+            false => output.push_str(&format!("{} (extends {})\n", name, extends.join(", "))),
+        }
+        let secret_keys = taskfile.context_secret_keys(&[name.to_owned()]);
+        let mut keys: Vec<_> = values.keys().collect();
+        keys.sort();
+        for key in keys {
+            let value = match secret_keys.contains(key) {
+                true => "***",
+                false => values[key].as_str(),
+            };
+            output.push_str(&format!("    {} = {}\n", key, value));
+        }
+    }
+    return Ok(output);
+}
+
+pub fn run(_matches: &ArgMatches, taskfile: &Taskfile) -> Result<(), UserFacingError> {
+    print!("{}", render(taskfile)?);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::utils::test_helpers::test_helpers::load_from_string;
+
+    #[test]
+    fn test_render() {
+        let output = super::render(&load_from_string()).unwrap();
+        assert_eq!(
+            output,
+            "test\n    test_key = test_value\ntest-child (extends test)\n    child_key = child\n    test_key = child_value\n"
+        );
+    }
+}
@@ -0,0 +1,384 @@
+use crate::{
+    taskfile::{TaskStanza, Taskfile},
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, ValueEnum};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum DocsFormat {
+    Markdown,
+    Man,
+    Html,
+}
+
+const GENERATED: &str = "generated by `tasker docs`, edit the Taskfile instead";
+
+// what the docs say about an arg, shared by every format
+struct ArgDoc {
+    name: String,
+    arg_type: String,
+    // `required`, or the default
+    value: String,
+    env: String,
+    choices: Vec<String>,
+}
+
+struct TaskDoc {
+    name: String,
+    description: Option<String>,
+    usage: String,
+    args: Vec<ArgDoc>,
+    contexts: Vec<String>,
+    tags: Vec<String>,
+    confirm: Option<String>,
+}
+impl TaskDoc {
+    // `program` is the name the CLI runs under, shown in the usage
+    fn new(task: &TaskStanza, program: &str) -> Self {
+        let mut usage = String::from(program);
+        // a task limited to some contexts can not run without one
+        if let Some(context) = task.contexts.first() {
+            usage.push_str(&format!(" -x {}", context));
+        }
+        usage.push_str(&format!(" {}", task.name));
+        for arg in task.get_command_args() {
+            match arg.is_required() {
+                true => usage.push_str(&format!(" <{}>", arg.name)),
+                false => usage.push_str(&format!(" [{}]", arg.name)),
+            }
+        }
+        let args = task
+            .get_command_args()
+            .iter()
+            .map(|arg| ArgDoc {
+                name: arg.name.to_owned(),
+                arg_type: arg.arg_type.to_owned(),
+                value: match &arg.default {
+                    Some(default) => format!("default `{}`", default),
+                    None => "required".to_string(),
+                },
+                env: arg.env_var(),
+                choices: arg.choices.to_owned(),
+            })
+            .collect();
+        TaskDoc {
+            name: task.name.to_owned(),
+            description: task.description.to_owned(),
+            usage,
+            args,
+            contexts: task.contexts.to_owned(),
+            tags: task.tags.to_owned(),
+            confirm: task.confirm.to_owned(),
+        }
+    }
+}
+
+// context names with what they extend, values are left out since they may be secret
+fn context_docs(taskfile: &Taskfile) -> Vec<(String, String)> {
+    return taskfile
+        .context_names()
+        .into_iter()
+        .map(|name| {
+            let context = &taskfile.contexts[name];
+            let mut notes = vec![];
+            if !context.extends.is_empty() {
+                notes.push(format!("extends {}", context.extends.join(", ")));
+            }
+            if taskfile.is_context_protected(name) {
+                notes.push("protected".to_string());
+            }
+            (name.to_string(), notes.join(", "))
+        })
+        .collect();
+}
+
+fn arg_details(arg: &ArgDoc) -> String {
+    let mut details = format!("{}, {}, env `{}`", arg.arg_type, arg.value, arg.env);
+    if !arg.choices.is_empty() {
+        details.push_str(&format!(", one of {}", arg.choices.join(", ")));
+    }
+    return details;
+}
+fn task_notes(task: &TaskDoc) -> Vec<String> {
+    let mut notes = vec![];
+    if !task.contexts.is_empty() {
+        notes.push(format!(
+            "Only runs under the contexts {}.",
+            task.contexts.join(", ")
+        ));
+    }
+    if let Some(question) = &task.confirm {
+        notes.push(format!("Asks before running: {}", question));
+    }
+    if !task.tags.is_empty() {
+        notes.push(format!("Tags: {}.", task.tags.join(", ")));
+    }
+    return notes;
+}
+
+pub fn render_markdown(taskfile: &Taskfile) -> String {
+    let mut output = format!("# Tasks\n\n<!-- {} -->\n", GENERATED);
+    for task in taskfile
+        .tasks
+        .iter()
+        .map(|task| TaskDoc::new(task, taskfile.cli_name()))
+    {
+        output.push_str(&format!("\n## `{}`\n\n", task.name));
+        if let Some(description) = &task.description {
+            output.push_str(&format!("{}\n\n", description));
+        }
+        output.push_str(&format!("```\n{}\n```\n", task.usage));
+        if !task.args.is_empty() {
+            output.push_str("\n| arg | type | value | env |\n| --- | --- | --- | --- |\n");
+            for arg in &task.args {
+                let mut arg_type = arg.arg_type.to_owned();
+                if !arg.choices.is_empty() {
+                    arg_type = format!("{}: {}", arg_type, arg.choices.join(", "));
+                }
+                output.push_str(&format!(
+                    "| `{}` | {} | {} | `{}` |\n",
+                    arg.name,
+                    arg_type.replace('|', "\\|"),
+                    arg.value.replace('|', "\\|"),
+                    arg.env
+                ));
+            }
+        }
+        for note in task_notes(&task) {
+            output.push_str(&format!("\n{}\n", note));
+        }
+    }
+    let contexts = context_docs(taskfile);
+    if !contexts.is_empty() {
+        output.push_str("\n## Contexts\n\n");
+        for (name, notes) in contexts {
+            match notes.is_empty() {
+                true => output.push_str(&format!("- `{}`\n", name)),
+                false => output.push_str(&format!("- `{}` ({})\n", name, notes)),
+            }
+        }
+    }
+    return output;
+}
+
+fn roff_escape(text: &str) -> String {
+    let escaped = text.replace('\\', "\\e").replace('-', "\\-");
+    // lines starting with a dot or quote would be read as requests
+    return match escaped.starts_with('.') || escaped.starts_with('\'') {
+        true => format!("\\&{}", escaped),
+        false => escaped,
+    };
+}
+
+// no date in the header so `--check` does not fail every day
+pub fn render_man(taskfile: &Taskfile) -> String {
+    let mut output = format!(".\\\" {}\n", GENERATED);
+    output.push_str(&format!(
+        ".TH TASKS 7 \"\" \"{}\" \"Taskfile\"\n",
+        roff_escape(taskfile.cli_name())
+    ));
+    output.push_str(".SH NAME\ntasks \\- tasks defined in the Taskfile\n");
+    output.push_str(".SH TASKS\n");
+    for task in taskfile
+        .tasks
+        .iter()
+        .map(|task| TaskDoc::new(task, taskfile.cli_name()))
+    {
+        output.push_str(&format!(".SS {}\n", roff_escape(&task.name)));
+        if let Some(description) = &task.description {
+            output.push_str(&format!("{}\n", roff_escape(description)));
+        }
+        output.push_str(&format!(".PP\n.B {}\n", roff_escape(&task.usage)));
+        for arg in &task.args {
+            output.push_str(&format!(
+                ".TP\n.B {}\n{}\n",
+                roff_escape(&arg.name),
+                roff_escape(&arg_details(arg).replace('`', ""))
+            ));
+        }
+        for note in task_notes(&task) {
+            output.push_str(&format!(".PP\n{}\n", roff_escape(&note)));
+        }
+    }
+    let contexts = context_docs(taskfile);
+    if !contexts.is_empty() {
+        output.push_str(".SH CONTEXTS\n");
+        for (name, notes) in contexts {
+            output.push_str(&format!(
+                ".TP\n.B {}\n{}\n",
+                roff_escape(&name),
+                roff_escape(&notes)
+            ));
+        }
+    }
+    return output;
+}
+
+fn html_escape(text: &str) -> String {
+    return text
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+}
+// backticks become code
+fn html_text(text: &str) -> String {
+    let mut output = String::new();
+    for (i, part) in html_escape(text).split('`').enumerate() {
+        match i % 2 {
+            1 => output.push_str(&format!("<code>{}</code>", part)),
+            _ => output.push_str(part),
+        }
+    }
+    return output;
+}
+
+pub fn render_html(taskfile: &Taskfile) -> String {
+    let mut output = format!(
+        "<!DOCTYPE html>\n<!-- {} -->\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>Tasks</title>\n</head>\n<body>\n<h1>Tasks</h1>\n",
+        html_escape(GENERATED)
+    );
+    for task in taskfile
+        .tasks
+        .iter()
+        .map(|task| TaskDoc::new(task, taskfile.cli_name()))
+    {
+        output.push_str(&format!(
+            "<h2 id=\"{0}\"><code>{0}</code></h2>\n",
+            html_escape(&task.name)
+        ));
+        if let Some(description) = &task.description {
+            output.push_str(&format!("<p>{}</p>\n", html_text(description)));
+        }
+        output.push_str(&format!("<pre>{}</pre>\n", html_escape(&task.usage)));
+        if !task.args.is_empty() {
+            output.push_str("<dl>\n");
+            for arg in &task.args {
+                output.push_str(&format!(
+                    "<dt><code>{}</code></dt><dd>{}</dd>\n",
+                    html_escape(&arg.name),
+                    html_text(&arg_details(arg))
+                ));
+            }
+            output.push_str("</dl>\n");
+        }
+        for note in task_notes(&task) {
+            output.push_str(&format!("<p>{}</p>\n", html_text(&note)));
+        }
+    }
+    let contexts = context_docs(taskfile);
+    if !contexts.is_empty() {
+        output.push_str("<h2>Contexts</h2>\n<ul>\n");
+        for (name, notes) in contexts {
+            output.push_str(&format!("<li><code>{}</code>", html_escape(&name)));
+            if !notes.is_empty() {
+                output.push_str(&format!(" ({})", html_escape(&notes)));
+            }
+            output.push_str("</li>\n");
+        }
+        output.push_str("</ul>\n");
+    }
+    output.push_str("</body>\n</html>\n");
+    return output;
+}
+
+pub fn render(taskfile: &Taskfile, format: DocsFormat) -> String {
+    return match format {
+        DocsFormat::Markdown => render_markdown(taskfile),
+        DocsFormat::Man => render_man(taskfile),
+        DocsFormat::Html => render_html(taskfile),
+    };
+}
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("docs")
+        .about("render documentation for every task of the Taskfile")
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(value_parser!(DocsFormat))
+                .default_value("markdown")
+                .help("output format"),
+        )
+        .arg(
+            Arg::new("out")
+                .long("out")
+                .short('o')
+                .value_parser(value_parser!(PathBuf))
+                .help("write to this file instead of printing"),
+        )
+        .arg(
+            Arg::new("check")
+                .long("check")
+                .action(ArgAction::SetTrue)
+                .requires("out")
+                .help("fail when the file given with --out is out of date instead of writing it"),
+        );
+}
+
+pub fn run(matches: &ArgMatches, taskfile: &Taskfile) -> Result<(), UserFacingError> {
+    let format = *matches.get_one::<DocsFormat>("format").unwrap();
+    let docs = render(taskfile, format);
+    let path = match matches.get_one::<PathBuf>("out") {
+        Some(path) => path,
+        None => {
+            print!("{}", docs);
+            return Ok(());
+        }
+    };
+    if matches.get_flag("check") {
+        if std::fs::read_to_string(path).ok().as_deref() == Some(docs.as_str()) {
+            return Ok(());
+        }
+        let format = format.to_possible_value().unwrap();
+        return Err(UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "DOCS_OUT_OF_DATE".to_string(),
+            messages: vec![format!(
+                "`{}` does not match the Taskfile, run `{} docs --format {} --out {}`",
+                path.display(),
+                taskfile.cli_name(),
+                format.get_name(),
+                path.display()
+            )],
+        }));
+    }
+    return std::fs::write(path, docs).map_err(|e| {
+        UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "WRITE_ERROR".to_string(),
+            messages: vec![format!("could not write `{}`: {}", path.display(), e)],
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render_html, render_man, render_markdown};
+    use crate::utils::test_helpers::test_helpers::load_from_string;
+
+    #[test]
+    fn test_render_markdown() {
+        let docs = render_markdown(&load_from_string());
+        assert!(docs.contains(
+            "\n## `test-cmd`\n\ngreets a user by name\n\n```\ntasker test-cmd <required_arg> [default_arg]\n```\n"
+        ));
+        assert!(docs
+            .contains("| `default_arg` | string | default `default` | `TASKER_DEFAULT_ARG` |\n"));
+        assert!(docs.contains("\n## Contexts\n\n- `test`\n- `test-child` (extends test)\n"));
+    }
+    #[test]
+    fn test_render_man() {
+        let docs = render_man(&load_from_string());
+        assert!(docs.contains(".SS test\\-cmd\n"));
+        assert!(docs.contains(".TP\n.B required_arg\nstring, required, env TASKER_REQUIRED_ARG\n"));
+    }
+    #[test]
+    fn test_render_html() {
+        let docs = render_html(&load_from_string());
+        assert!(docs.contains("<pre>tasker test-cmd &lt;required_arg&gt; [default_arg]</pre>"));
+        assert!(docs.contains(
+            "<dd>string, default <code>default</code>, env <code>TASKER_DEFAULT_ARG</code></dd>"
+        ));
+        assert!(docs.ends_with("</html>\n"));
+    }
+}
@@ -0,0 +1,87 @@
+use crate::{
+    run::TaskBuilder,
+    taskfile::Taskfile,
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::{Arg, ArgMatches};
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("explain")
+        .about("show where each variable of a task gets its value from")
+        .arg(Arg::new("task").required(true).help("task to explain"))
+        .arg(
+            Arg::new("args")
+                .num_args(0..)
+                .trailing_var_arg(true)
+                .allow_hyphen_values(true)
+                .help("args for the task, as they would be passed to it"),
+        );
+}
+
+// each variable with its final value, the source that won and the ones it shadowed
+pub fn render(builder: &TaskBuilder, task_name: &str) -> String {
+    let contexts = builder.context_names();
+    let mut output = match contexts.is_empty() {
+        true => format!("task `{}` without a context\n", task_name),
+        false => format!("task `{}` under {}\n", task_name, contexts.join(", ")),
+    };
+    if builder.variable_sources().is_empty() {
+        output.push_str("\nno variables\n");
+    }
+    for (name, sources) in builder.variable_sources() {
+        let (winner, shadowed) = sources.split_last().unwrap();
+        output.push_str(&format!("\n{} = {}\n", name, winner.value));
+        output.push_str(&format!("    from {}\n", winner));
+        for source in shadowed.iter().rev() {
+            output.push_str(&format!("    shadows `{}` from {}\n", source.value, source));
+        }
+    }
+    return builder.secrets().mask(&output);
+}
+
+pub fn run(
+    matches: &ArgMatches,
+    taskfile: &Taskfile,
+    global: &ArgMatches,
+) -> Result<(), UserFacingError> {
+    let task_name = matches.get_one::<String>("task").unwrap();
+    if taskfile.get_task_by_name(task_name).is_none() {
+        return Err(UserFacingError::TaskDoesNotExist(ErrWithMessage {
+            code: "TASK_ERROR".to_string(),
+            messages: vec![format!("task `{}` does not exist", task_name)],
+        }));
+    }
+    let mut raw_args = vec![task_name.to_owned()];
+    if let Some(args) = matches.get_many::<String>("args") {
+        raw_args.extend(args.cloned());
+    }
+    let mut builder = TaskBuilder::new(taskfile.to_owned());
+    builder.plan_from_args(&raw_args, global)?;
+    print!("{}", render(&builder, task_name));
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::{
+        cliargs::CliArgs, run::TaskBuilder, utils::test_helpers::test_helpers::load_from_string,
+    };
+    use clap::CommandFactory;
+
+    #[test]
+    fn test_render() {
+        let global = CliArgs::command().get_matches_from(["tasker", "-x", "test,test-child"]);
+        let mut builder = TaskBuilder::new(load_from_string());
+        let raw_args = ["test-cmd".to_string(), "Foo".to_string()];
+        builder.plan_from_args(&raw_args, &global).unwrap();
+        let output = render(&builder, "test-cmd");
+        assert!(output.starts_with("task `test-cmd` under test, test-child\n"));
+        assert!(output.contains("\nrequired_arg = Foo\n    from command line\n"));
+        assert!(output.contains("\ndefault_arg = default\n    from default of task `test-cmd`\n"));
+        assert!(output.contains(
+            "\ntest_key = child_value\n    from context `test-child`\n    shadows `test_value` from context `test`\n"
+        ));
+        assert!(output.contains("\nchild_key = child\n    from context `test-child`\n"));
+    }
+}
@@ -0,0 +1,94 @@
+use crate::{
+    run::{render_makefile, render_sh, TaskBuilder},
+    taskfile::Taskfile,
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::{value_parser, Arg, ArgMatches, ValueEnum};
+use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ExportFormat {
+    Make,
+    Sh,
+}
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("export")
+        .about("compile the tasks into a Makefile or a shell script that runs without tasker")
+        .arg(
+            Arg::new("to")
+                .long("to")
+                .required(true)
+                .value_parser(value_parser!(ExportFormat))
+                .help("the kind of file to write"),
+        )
+        .arg(
+            Arg::new("out")
+                .long("out")
+                .short('o')
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(clap::ValueHint::FilePath)
+                .help("write to a file instead of stdout"),
+        );
+}
+
+pub fn render(
+    taskfile: &Taskfile,
+    format: ExportFormat,
+    context_names: Vec<String>,
+) -> Result<String, UserFacingError> {
+    let builder = TaskBuilder::new(taskfile.to_owned());
+    let (context_names, tasks) = builder.export_tasks(context_names)?;
+    return Ok(match format {
+        ExportFormat::Make => render_makefile(&tasks, &context_names),
+        ExportFormat::Sh => render_sh(&tasks, &context_names),
+    });
+}
+
+pub fn run(
+    matches: &ArgMatches,
+    taskfile: &Taskfile,
+    global: &ArgMatches,
+) -> Result<(), UserFacingError> {
+    let format = *matches.get_one::<ExportFormat>("to").unwrap();
+    let context_names = match global.get_many::<String>("context") {
+        Some(names) => names.cloned().collect(),
+        None => vec![],
+    };
+    let output = render(taskfile, format, context_names)?;
+    let path = match matches.get_one::<PathBuf>("out") {
+        Some(path) => path,
+        None => {
+            print!("{}", output);
+            return Ok(());
+        }
+    };
+    let mut written = std::fs::write(path, output);
+    // the script is meant to be run directly
+    if format == ExportFormat::Sh {
+        written = written
+            .and_then(|_| std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)));
+    }
+    return written.map_err(|e| {
+        UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "WRITE_ERROR".to_string(),
+            messages: vec![format!("could not write `{}`: {}", path.display(), e)],
+        })
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, ExportFormat};
+    use crate::utils::test_helpers::test_helpers::load_from_string;
+
+    #[test]
+    fn test_render() {
+        let taskfile = load_from_string();
+        let script = render(&taskfile, ExportFormat::Sh, vec![]).unwrap();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        let makefile = render(&taskfile, ExportFormat::Make, vec!["test".to_string()]).unwrap();
+        assert!(makefile.contains("# contexts: test\n"));
+        assert!(render(&taskfile, ExportFormat::Make, vec!["nope".to_string()]).is_err());
+    }
+}
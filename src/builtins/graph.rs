@@ -0,0 +1,276 @@
+use crate::{
+    run::{PlanItem, TaskBuilder, TaskPlan},
+    taskfile::Taskfile,
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::{value_parser, Arg, ArgMatches, ValueEnum};
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum GraphFormat {
+    Tree,
+    Dot,
+    Mermaid,
+}
+
+// how one task reaches another
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum EdgeKind {
+    Call,
+    OnFail,
+    Finally,
+}
+impl EdgeKind {
+    fn label(&self) -> &'static str {
+        match self {
+            EdgeKind::Call => "",
+            EdgeKind::OnFail => "on_fail: ",
+            EdgeKind::Finally => "finally: ",
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct Edge {
+    from: String,
+    to: String,
+    args: String,
+    kind: EdgeKind,
+}
+
+// sub tasks of a plan with the way they are reached, in the order they run
+fn children(plan: &TaskPlan) -> Vec<(EdgeKind, &TaskPlan)> {
+    let mut children = vec![];
+    for (kind, items) in [
+        (EdgeKind::Call, &plan.items),
+        (EdgeKind::OnFail, &plan.on_fail),
+        (EdgeKind::Finally, &plan.finally),
+    ] {
+        for item in items {
+            if let PlanItem::Task(sub_plan) = item {
+                children.push((kind, sub_plan));
+            }
+        }
+    }
+    children
+}
+
+fn collect_edges(plan: &TaskPlan, nodes: &mut Vec<String>, edges: &mut Vec<Edge>) {
+    if !nodes.contains(&plan.name) {
+        nodes.push(plan.name.to_owned());
+    }
+    for (kind, sub_plan) in children(plan) {
+        let edge = Edge {
+            from: plan.name.to_owned(),
+            to: sub_plan.name.to_owned(),
+            args: sub_plan.args.join(" "),
+            kind,
+        };
+        if !edges.contains(&edge) {
+            edges.push(edge);
+        }
+        collect_edges(sub_plan, nodes, edges);
+    }
+}
+
+fn render_tree(plan: &TaskPlan, prefix: &str, output: &mut String) {
+    let children = children(plan);
+    for (i, (kind, sub_plan)) in children.iter().enumerate() {
+        let last = i == children.len() - 1;
+        let mut label = format!("{}{}", kind.label(), sub_plan.name);
+        if !sub_plan.args.is_empty() {
+            label.push_str(&format!(" {}", sub_plan.args.join(" ")));
+        }
+        let (branch, indent) = match last {
+            true => ("└── ", "    "),
+            false => ("├── ", "│   "),
+        };
+        output.push_str(&format!("{}{}{}\n", prefix, branch, label));
+        render_tree(sub_plan, &format!("{}{}", prefix, indent), output);
+    }
+}
+
+fn render_dot(nodes: &[String], edges: &[Edge]) -> String {
+    let quote = |text: &str| format!("\"{}\"", text.replace('"', "\\\""));
+    let mut output = String::from("digraph tasks {\n");
+    for node in nodes {
+        output.push_str(&format!("    {};\n", quote(node)));
+    }
+    for edge in edges {
+        let mut attributes = vec![];
+        if !edge.args.is_empty() {
+            attributes.push(format!("label={}", quote(&edge.args)));
+        }
+        if edge.kind != EdgeKind::Call {
+            attributes.push("style=dashed".to_string());
+            attributes.push(format!(
+                "xlabel={}",
+                quote(edge.kind.label().trim_end_matches(": "))
+            ));
+        }
+        let attributes = match attributes.is_empty() {
+            true => String::new(),
+            false => format!(" [{}]", attributes.join(", ")),
+        };
+        output.push_str(&format!(
+            "    {} -> {}{};\n",
+            quote(&edge.from),
+            quote(&edge.to),
+            attributes
+        ));
+    }
+    output.push_str("}\n");
+    output
+}
+
+fn render_mermaid(nodes: &[String], edges: &[Edge]) -> String {
+    // task names can hold characters mermaid ids can not, so nodes are numbered
+    let id = |name: &str| format!("n{}", nodes.iter().position(|node| node == name).unwrap());
+    let escape = |text: &str| text.replace('"', "#quot;");
+    let mut output = String::from("graph TD\n");
+    for node in nodes {
+        output.push_str(&format!("    {}[\"{}\"]\n", id(node), escape(node)));
+    }
+    for edge in edges {
+        let arrow = match edge.kind {
+            EdgeKind::Call => "-->",
+            _ => "-.->",
+        };
+        let label = format!("{}{}", edge.kind.label(), edge.args);
+        let label = label.trim_end_matches(": ");
+        match label.is_empty() {
+            true => output.push_str(&format!(
+                "    {} {} {}\n",
+                id(&edge.from),
+                arrow,
+                id(&edge.to)
+            )),
+            false => output.push_str(&format!(
+                "    {} {}|\"{}\"| {}\n",
+                id(&edge.from),
+                arrow,
+                escape(label),
+                id(&edge.to)
+            )),
+        }
+    }
+    output
+}
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("graph")
+        .about("show which tasks call which, for one task or the whole Taskfile")
+        .arg(Arg::new("task").help("only show what this task expands into"))
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(value_parser!(GraphFormat))
+                .default_value("tree")
+                .help("output format"),
+        );
+}
+
+pub fn render(
+    taskfile: &Taskfile,
+    task_name: Option<&str>,
+    format: GraphFormat,
+) -> Result<String, UserFacingError> {
+    let builder = TaskBuilder::new(taskfile.to_owned());
+    let plans: Vec<TaskPlan> = match task_name {
+        Some(name) => match taskfile.get_task_by_name(name) {
+            Some(task) => vec![builder.plan_with_defaults(task)?],
+            None => {
+                return Err(UserFacingError::TaskDoesNotExist(ErrWithMessage {
+                    code: "TASK_ERROR".to_string(),
+                    messages: vec![format!("task `{}` does not exist", name)],
+                }))
+            }
+        },
+        None => taskfile
+            .tasks
+            .iter()
+            .map(|task| builder.plan_with_defaults(task))
+            .collect::<Result<_, _>>()?,
+    };
+    let mut nodes = vec![];
+    let mut edges = vec![];
+    for plan in &plans {
+        collect_edges(plan, &mut nodes, &mut edges);
+    }
+    let output = match format {
+        GraphFormat::Dot => render_dot(&nodes, &edges),
+        GraphFormat::Mermaid => render_mermaid(&nodes, &edges),
+        GraphFormat::Tree => {
+            let mut output = String::new();
+            // a task called by another one already shows up in that task's tree
+            for plan in plans.iter().filter(|plan| {
+                task_name.is_some() || !edges.iter().any(|edge| edge.to == plan.name)
+            }) {
+                output.push_str(&format!("{}\n", plan.name));
+                render_tree(plan, "", &mut output);
+            }
+            output
+        }
+    };
+    return Ok(output);
+}
+
+pub fn run(matches: &ArgMatches, taskfile: &Taskfile) -> Result<(), UserFacingError> {
+    let task_name = matches.get_one::<String>("task").map(String::as_str);
+    let format = *matches.get_one::<GraphFormat>("format").unwrap();
+    print!("{}", render(taskfile, task_name, format)?);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, GraphFormat};
+    use crate::{taskfile::Taskfile, utils::test_helpers::test_helpers::load_from_string};
+
+    #[test]
+    fn test_render_tree() {
+        let taskfile = load_from_string();
+        let output = render(&taskfile, Some("test-task"), GraphFormat::Tree).unwrap();
+        assert_eq!(
+            output,
+            "test-task\n├── test-cmd Foo Bar\n└── test-cmd Bar Foo\n"
+        );
+        let output = render(&taskfile, None, GraphFormat::Tree).unwrap();
+        assert!(output.contains("test-cleanup\n└── on_fail: test-cmd Foo\n"));
+        assert!(!output.starts_with("test-cmd\n"));
+    }
+    #[test]
+    fn test_render_dot() {
+        let taskfile = load_from_string();
+        let output = render(&taskfile, Some("test-cleanup"), GraphFormat::Dot).unwrap();
+        assert_eq!(
+            output,
+            "digraph tasks {\n    \"test-cleanup\";\n    \"test-cmd\";\n    \"test-cleanup\" -> \"test-cmd\" [label=\"Foo\", style=dashed, xlabel=\"on_fail\"];\n}\n"
+        );
+    }
+    #[test]
+    fn test_render_mermaid() {
+        let taskfile = load_from_string();
+        let output = render(&taskfile, Some("test-task"), GraphFormat::Mermaid).unwrap();
+        assert_eq!(
+            output,
+            "graph TD\n    n0[\"test-task\"]\n    n1[\"test-cmd\"]\n    n0 -->|\"Foo Bar\"| n1\n    n0 -->|\"Bar Foo\"| n1\n"
+        );
+        assert!(render(&taskfile, Some("nope"), GraphFormat::Mermaid).is_err());
+    }
+    #[test]
+    fn test_render_unresolved_call() {
+        let yaml = r#"
+        tasks:
+          - name: dispatch
+            commands:
+              - task: ${target} now
+            args:
+              - {name: target, type: string}
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let output = render(&taskfile, Some("dispatch"), GraphFormat::Tree).unwrap();
+        assert_eq!(output, "dispatch\n└── ${target} now\n");
+        let output = render(&taskfile, None, GraphFormat::Dot).unwrap();
+        assert!(output.contains("\"dispatch\" -> \"${target}\" [label=\"now\"];"));
+    }
+}
@@ -0,0 +1,125 @@
+use crate::{
+    taskfile::{parse_file, ImportFormat, ImportedTask},
+    utils::{
+        errors::{ErrWithMessage, UserFacingError},
+        strings::yaml_string,
+    },
+};
+use clap::{value_parser, Arg, ArgMatches, ValueEnum};
+use std::path::PathBuf;
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("import")
+        .about("convert the targets of a Makefile, justfile or package.json into Taskfile tasks")
+        .arg(
+            Arg::new("from")
+                .long("from")
+                .required(true)
+                .value_parser(value_parser!(ImportFormat))
+                .help("the tool the file belongs to"),
+        )
+        .arg(
+            Arg::new("file")
+                .value_parser(value_parser!(PathBuf))
+                .value_hint(clap::ValueHint::FilePath)
+                .help("file to convert, the tool's usual file name when left out"),
+        );
+}
+
+fn render_task(task: &ImportedTask) -> String {
+    let mut output = format!("  - name: {}\n", yaml_string(&task.name));
+    if let Some(description) = &task.description {
+        output.push_str(&format!("    description: {}\n", yaml_string(description)));
+    }
+    let args: Vec<_> = task.args.iter().chain(task.variables.iter()).collect();
+    match args.is_empty() {
+        true => output.push_str("    args: []\n"),
+        false => output.push_str("    args:\n"),
+    }
+    for (name, default) in args {
+        output.push_str(&format!("      - name: {}\n        type: string\n", name));
+        if let Some(default) = default {
+            output.push_str(&format!("        default: {}\n", yaml_string(default)));
+        }
+    }
+    let calls = |names: &[String]| -> Vec<String> {
+        return names
+            .iter()
+            .map(|name| format!("      - task: {}\n", yaml_string(name)))
+            .collect();
+    };
+    let mut commands = calls(&task.deps);
+    for command in &task.commands {
+        commands.push(format!(
+            "      - shell: {}\n",
+            yaml_string(&command.command)
+        ));
+        if command.ignore_error {
+            commands.push("        ignore_error: true\n".to_string());
+        }
+    }
+    commands.extend(calls(&task.after));
+    match commands.is_empty() {
+        true => output.push_str("    commands: []\n"),
+        false => output.push_str(&format!("    commands:\n{}", commands.concat())),
+    }
+    return output;
+}
+
+pub fn render(tasks: &[ImportedTask], source: &str, format: &str) -> String {
+    let mut output = format!(
+        "# imported from {} with `tasker import --from {}`\ntasks:\n",
+        source, format
+    );
+    for task in tasks {
+        output.push_str(&render_task(task));
+    }
+    return output;
+}
+
+pub fn run(matches: &ArgMatches) -> Result<(), UserFacingError> {
+    let format = *matches.get_one::<ImportFormat>("from").unwrap();
+    let path = match matches.get_one::<PathBuf>("file") {
+        Some(path) => path.to_owned(),
+        None => PathBuf::from(format.default_file()),
+    };
+    let tasks = parse_file(format, &path).map_err(|e| {
+        UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "IMPORT_ERROR".to_string(),
+            messages: vec![e],
+        })
+    })?;
+    let name = format.to_possible_value().unwrap();
+    print!(
+        "{}",
+        render(&tasks, &path.display().to_string(), name.get_name())
+    );
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::taskfile::{parse_file, ImportFormat, Taskfile};
+
+    #[test]
+    fn test_render() {
+        let dir = std::env::temp_dir().join(format!("tasker-import-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(
+            dir.join("Makefile"),
+            "PORT = 8080\n.PHONY: all\nall: serve\n\n# serve the docs\nserve:\n\t-python -m http.server $(PORT)\n",
+        )
+        .unwrap();
+        let tasks = parse_file(ImportFormat::Make, &dir.join("Makefile")).unwrap();
+        let rendered = render(&tasks, "Makefile", "make");
+        std::fs::write(dir.join("Taskfile"), &rendered).unwrap();
+        let taskfile = Taskfile::new(dir.join("Taskfile").to_string_lossy().to_string());
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(taskfile.is_ok());
+        assert_eq!(
+            rendered,
+            "# imported from Makefile with `tasker import --from make`\ntasks:\n  - name: all\n    args: []\n    commands:\n      - task: serve\n  - name: serve\n    description: serve the docs\n    args:\n      - name: PORT\n        type: string\n        default: \"8080\"\n    commands:\n      - shell: python -m http.server ${PORT}\n        ignore_error: true\n"
+        );
+    }
+}
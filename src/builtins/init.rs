@@ -0,0 +1,141 @@
+use crate::{
+    taskfile::{detect, Detected},
+    utils::{
+        errors::{ErrWithMessage, UserFacingError},
+        strings::yaml_string,
+    },
+};
+use clap::{Arg, ArgAction, ArgMatches};
+use std::path::{Path, PathBuf};
+
+const STARTER: &str = r#"# tasks for this project, `tasker list` shows them and `tasker <task>` runs one
+tasks:
+  - name: hello
+    description: "greet someone"
+    args:
+      - name: name
+        type: string
+        default: world
+    commands:
+      - shell: echo Hello ${name}
+"#;
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("init")
+        .about("write a starter Taskfile, to the path given with -c")
+        .arg(
+            Arg::new("detect")
+                .long("detect")
+                .action(ArgAction::SetTrue)
+                .help("generate tasks for the project files found next to the Taskfile"),
+        )
+        .arg(
+            Arg::new("force")
+                .long("force")
+                .short('f')
+                .action(ArgAction::SetTrue)
+                .help("overwrite an existing Taskfile"),
+        );
+}
+
+// names are kept short (`build`) unless an earlier project already took one of them, then
+// all tasks of the project get the tool as namespace (`docker:build`, `docker:run`)
+pub fn render(detected: &[Detected]) -> String {
+    if detected.is_empty() {
+        return STARTER.to_string();
+    }
+    let files: Vec<_> = detected.iter().map(|detected| detected.file).collect();
+    let mut output = format!("# tasks generated from {}\ntasks:\n", files.join(", "));
+    let mut names: Vec<String> = vec![];
+    for project in detected {
+        let clashes = project.tasks.iter().any(|task| names.contains(&task.name));
+        for task in &project.tasks {
+            let name = match clashes {
+                true => format!("{}:{}", project.namespace, task.name),
+                false => task.name.to_owned(),
+            };
+            output.push_str(&format!(
+                "  - name: {}\n    description: {}\n    args: []\n    commands:\n      - shell: {}\n",
+                yaml_string(&name),
+                yaml_string(&task.description),
+                yaml_string(&task.command)
+            ));
+            names.push(name);
+        }
+    }
+    return output;
+}
+
+fn write_error(path: &Path, e: std::io::Error) -> UserFacingError {
+    return UserFacingError::TaskExecutionError(ErrWithMessage {
+        code: "WRITE_ERROR".to_string(),
+        messages: vec![format!("could not write `{}`: {}", path.display(), e)],
+    });
+}
+
+// `global` holds `-c`, the Taskfile is written where tasker would look for it
+pub fn run(matches: &ArgMatches, global: &ArgMatches) -> Result<(), UserFacingError> {
+    let path = global
+        .get_one::<PathBuf>("config_path")
+        .cloned()
+        .unwrap_or_else(|| PathBuf::from("Taskfile"));
+    if path.exists() && !matches.get_flag("force") {
+        return Err(UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "TASKFILE_EXISTS".to_string(),
+            messages: vec![format!(
+                "`{}` already exists, pass --force to overwrite it",
+                path.display()
+            )],
+        }));
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+    let detected = match matches.get_flag("detect") {
+        true => detect(&dir),
+        false => vec![],
+    };
+    std::fs::write(&path, render(&detected)).map_err(|e| write_error(&path, e))?;
+    match detected.is_empty() {
+        true => println!("Taskfile written to {}", path.display()),
+        false => {
+            let files: Vec<_> = detected.iter().map(|detected| detected.file).collect();
+            println!(
+                "Taskfile written to {} with tasks for {}",
+                path.display(),
+                files.join(", ")
+            );
+        }
+    }
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::render;
+    use crate::taskfile::{detect, Taskfile};
+
+    #[test]
+    fn test_render() {
+        let dir = std::env::temp_dir().join(format!("tasker-init-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        std::fs::write(dir.join("Dockerfile"), "FROM scratch\n").unwrap();
+        let detected = detect(&dir);
+        let path = dir.join("Taskfile");
+        for rendered in [render(&detected), render(&[])] {
+            std::fs::write(&path, rendered).unwrap();
+            let taskfile = Taskfile::new(path.to_string_lossy().to_string());
+            assert!(taskfile.is_ok());
+        }
+        let _ = std::fs::remove_dir_all(&dir);
+        let rendered = render(&detected);
+        assert!(rendered.starts_with("# tasks generated from Cargo.toml, Dockerfile\n"));
+        assert!(rendered.contains(
+            "  - name: build\n    description: build with cargo\n    args: []\n    commands:\n      - shell: cargo build\n"
+        ));
+        assert!(rendered.contains("  - name: docker:build\n"));
+        assert!(rendered.contains("  - name: docker:run\n"));
+    }
+}
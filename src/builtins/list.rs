@@ -0,0 +1,158 @@
+use crate::{
+    taskfile::{TaskStanza, Taskfile},
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::{value_parser, Arg, ArgAction, ArgMatches, ValueEnum};
+use serde::Serialize;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum ListFormat {
+    Plain,
+    Json,
+    Yaml,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct ArgListing {
+    name: String,
+    #[serde(rename = "type")]
+    arg_type: String,
+    default: Option<String>,
+    required: bool,
+    env: String,
+}
+
+#[derive(Serialize, Debug, PartialEq)]
+struct TaskListing {
+    name: String,
+    description: Option<String>,
+    namespace: Option<String>,
+    tags: Vec<String>,
+    source: Option<String>,
+    args: Vec<ArgListing>,
+}
+impl From<&TaskStanza> for TaskListing {
+    fn from(task: &TaskStanza) -> Self {
+        let args = task
+            .get_command_args()
+            .iter()
+            .map(|arg| ArgListing {
+                name: arg.name.to_owned(),
+                arg_type: arg.arg_type.to_owned(),
+                default: arg.default.to_owned(),
+                required: arg.is_required(),
+                env: arg.env_var(),
+            })
+            .collect();
+        TaskListing {
+            name: task.name.to_owned(),
+            description: task.description.to_owned(),
+            namespace: task.namespace().map(String::from),
+            tags: task.tags.to_owned(),
+            source: task
+                .source
+                .as_ref()
+                .map(|source| source.display().to_string()),
+            args,
+        }
+    }
+}
+
+pub fn clap_command() -> clap::Command {
+    return clap::Command::new("list")
+        .about("list the tasks of the Taskfile with their arguments")
+        .arg(
+            Arg::new("format")
+                .long("format")
+                .value_parser(value_parser!(ListFormat))
+                .default_value("plain")
+                .help("output format"),
+        )
+        .arg(
+            Arg::new("json")
+                .long("json")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("format")
+                .help("shorthand for --format json"),
+        );
+}
+
+fn render_plain(listings: &[TaskListing]) -> String {
+    let mut output = String::new();
+    for task in listings {
+        output.push_str(&task.name);
+        if let Some(description) = &task.description {
+            output.push_str(&format!(" - {}", description));
+        }
+        if !task.tags.is_empty() {
+            output.push_str(&format!(" [{}]", task.tags.join(", ")));
+        }
+        output.push('\n');
+        for arg in &task.args {
+            let default = match &arg.default {
+                Some(default) => format!("default `{}`", default),
+                None => "required".to_string(),
+            };
+            output.push_str(&format!(
+                "    {}: {}, {} ({})\n",
+                arg.name, arg.arg_type, default, arg.env
+            ));
+        }
+    }
+    return output;
+}
+
+pub fn render(taskfile: &Taskfile, format: ListFormat) -> Result<String, UserFacingError> {
+    let listings: Vec<TaskListing> = taskfile.tasks.iter().map(TaskListing::from).collect();
+    let rendered = match format {
+        ListFormat::Plain => Ok(render_plain(&listings)),
+        ListFormat::Json => serde_json::to_string_pretty(&listings)
+            .map(|json| json + "\n")
+            .map_err(|e| e.to_string()),
+        ListFormat::Yaml => serde_yaml::to_string(&listings).map_err(|e| e.to_string()),
+    };
+    return rendered.map_err(|e| {
+        UserFacingError::TaskExecutionError(ErrWithMessage {
+            code: "RENDER_ERROR".to_string(),
+            messages: vec![format!("could not render the task list: {}", e)],
+        })
+    });
+}
+
+pub fn run(matches: &ArgMatches, taskfile: &Taskfile) -> Result<(), UserFacingError> {
+    let format = match matches.get_flag("json") {
+        true => ListFormat::Json,
+        false => *matches.get_one::<ListFormat>("format").unwrap(),
+    };
+    print!("{}", render(taskfile, format)?);
+    return Ok(());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{render, ListFormat};
+    use crate::utils::test_helpers::test_helpers::load_from_string;
+
+    #[test]
+    fn test_render_plain() {
+        let output = render(&load_from_string(), ListFormat::Plain).unwrap();
+        assert!(output.starts_with(
+            "test-cmd - greets a user by name\n    required_arg: string, required (TASKER_REQUIRED_ARG)\n    default_arg: string, default `default` (TASKER_DEFAULT_ARG)\n"
+        ));
+    }
+    #[test]
+    fn test_render_json() {
+        let output = render(&load_from_string(), ListFormat::Json).unwrap();
+        let tasks: serde_json::Value = serde_json::from_str(&output).unwrap();
+        assert_eq!(tasks[0]["name"], "test-cmd");
+        assert_eq!(tasks[0]["args"][0]["required"], true);
+        assert_eq!(tasks[0]["args"][1]["env"], "TASKER_DEFAULT_ARG");
+        assert_eq!(tasks[0]["namespace"], serde_json::Value::Null);
+    }
+    #[test]
+    fn test_render_yaml() {
+        let output = render(&load_from_string(), ListFormat::Yaml).unwrap();
+        let tasks: serde_yaml::Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(tasks[1]["name"].as_str(), Some("test-task"));
+    }
+}
@@ -0,0 +1,180 @@
+mod build_cli;
+mod complete;
+mod completions;
+mod contexts;
+mod docs;
+mod explain;
+mod export;
+mod graph;
+mod import;
+mod init;
+mod list;
+
+use crate::{
+    taskfile::Taskfile,
+    utils::errors::{ErrWithMessage, UserFacingError},
+};
+use clap::ArgMatches;
+
+// commands tasker provides on its own next to the tasks of the Taskfile, a task with the
+// same name takes precedence
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Builtin {
+    BuildCli,
+    Complete,
+    Completions,
+    Contexts,
+    Docs,
+    Explain,
+    Export,
+    Graph,
+    Import,
+    Init,
+    List,
+}
+impl Builtin {
+    const ALL: [Builtin; 11] = [
+        Builtin::BuildCli,
+        Builtin::Complete,
+        Builtin::Completions,
+        Builtin::Contexts,
+        Builtin::Docs,
+        Builtin::Explain,
+        Builtin::Export,
+        Builtin::Graph,
+        Builtin::Import,
+        Builtin::Init,
+        Builtin::List,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Builtin::BuildCli => "build-cli",
+            Builtin::Complete => "__complete",
+            Builtin::Completions => "completions",
+            Builtin::Contexts => "contexts",
+            Builtin::Docs => "docs",
+            Builtin::Explain => "explain",
+            Builtin::Export => "export",
+            Builtin::Graph => "graph",
+            Builtin::Import => "import",
+            Builtin::Init => "init",
+            Builtin::List => "list",
+        }
+    }
+    fn clap_command(&self) -> clap::Command {
+        match self {
+            Builtin::BuildCli => build_cli::clap_command(),
+            Builtin::Complete => complete::clap_command(),
+            Builtin::Completions => completions::clap_command(),
+            Builtin::Contexts => contexts::clap_command(),
+            Builtin::Docs => docs::clap_command(),
+            Builtin::Explain => explain::clap_command(),
+            Builtin::Export => export::clap_command(),
+            Builtin::Graph => graph::clap_command(),
+            Builtin::Import => import::clap_command(),
+            Builtin::Init => init::clap_command(),
+            Builtin::List => list::clap_command(),
+        }
+    }
+    // the builtin `task_info` asks for, unless the Taskfile defines a task by that name
+    pub fn from_task_info(task_info: &[String], taskfile: &Taskfile) -> Option<Builtin> {
+        let name = task_info.first()?;
+        if taskfile.get_task_by_name(name).is_some() {
+            return None;
+        }
+        return Self::ALL.into_iter().find(|builtin| builtin.name() == name);
+    }
+    // builtins that work without a Taskfile, e.g. to set up completions anywhere
+    fn needs_taskfile(&self) -> bool {
+        return !matches!(
+            self,
+            Builtin::Complete | Builtin::Completions | Builtin::Import | Builtin::Init
+        );
+    }
+    // the builtin `task_info` asks for when no Taskfile could be loaded
+    pub fn without_taskfile(task_info: &[String]) -> Option<Builtin> {
+        let name = task_info.first()?;
+        return Self::ALL
+            .into_iter()
+            .find(|builtin| builtin.name() == name && !builtin.needs_taskfile());
+    }
+    fn require(taskfile: Option<&Taskfile>) -> Result<&Taskfile, UserFacingError> {
+        return taskfile.ok_or_else(|| {
+            UserFacingError::TaskfileDoesNotExist(ErrWithMessage {
+                code: "INVALID_TASKFILE_PATH".to_string(),
+                messages: vec!["Taskfile does not exist".to_string()],
+            })
+        });
+    }
+    // builtins that are not shadowed by a task, for the help output
+    pub fn clap_commands(taskfile: &Taskfile) -> Vec<clap::Command> {
+        return Self::ALL
+            .into_iter()
+            .filter(|builtin| taskfile.get_task_by_name(builtin.name()).is_none())
+            .map(|builtin| builtin.clap_command())
+            .collect();
+    }
+    fn parse_args(&self, task_info: &[String]) -> Result<ArgMatches, UserFacingError> {
+        return match self.clap_command().try_get_matches_from(task_info) {
+            Ok(matches) => Ok(matches),
+            // --help is not an error, print it the way clap normally would
+            Err(e) if !e.use_stderr() => e.exit(),
+            Err(e) => Err(UserFacingError::MissingArgError(ErrWithMessage {
+                code: "INVALID_ARGUMENT".to_string(),
+                messages: vec!["\n".to_owned() + &e.render().to_string()],
+            })),
+        };
+    }
+    // `global` holds the options given before the command, like `-x`, `program` is the name
+    // the CLI runs under
+    pub fn run(
+        &self,
+        task_info: &[String],
+        taskfile: Option<&Taskfile>,
+        global: &ArgMatches,
+        program: &str,
+    ) -> Result<(), UserFacingError> {
+        let matches = self.parse_args(task_info)?;
+        return match self {
+            Builtin::BuildCli => build_cli::run(&matches, Self::require(taskfile)?),
+            Builtin::Complete => complete::run(&matches, taskfile),
+            Builtin::Completions => completions::run(&matches, program),
+            Builtin::Contexts => contexts::run(&matches, Self::require(taskfile)?),
+            Builtin::Docs => docs::run(&matches, Self::require(taskfile)?),
+            Builtin::Explain => explain::run(&matches, Self::require(taskfile)?, global),
+            Builtin::Export => export::run(&matches, Self::require(taskfile)?, global),
+            Builtin::Graph => graph::run(&matches, Self::require(taskfile)?),
+            Builtin::Import => import::run(&matches),
+            Builtin::Init => init::run(&matches, global),
+            Builtin::List => list::run(&matches, Self::require(taskfile)?),
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Builtin;
+    use crate::utils::test_helpers::test_helpers::load_from_string;
+
+    #[test]
+    fn test_from_task_info() {
+        let mut taskfile = load_from_string();
+        let task_info = vec!["contexts".to_string()];
+        assert_eq!(
+            Builtin::from_task_info(&task_info, &taskfile),
+            Some(Builtin::Contexts)
+        );
+        assert_eq!(
+            Builtin::from_task_info(&["test-cmd".to_string()], &taskfile),
+            None
+        );
+        // tasks shadow builtins
+        taskfile.tasks[0].name = "contexts".to_string();
+        assert_eq!(Builtin::from_task_info(&task_info, &taskfile), None);
+        assert_eq!(
+            Builtin::clap_commands(&taskfile).len(),
+            Builtin::ALL.len() - 1
+        );
+    }
+}
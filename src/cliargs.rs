@@ -19,13 +19,39 @@ pub struct CliArgs {
     #[arg(
         short = 'x',
         long = "context",
-        help = "execution context to load for command"
+        env = "TASKER_CONTEXT",
+        value_delimiter = ',',
+        help = "execution context to load for command, repeat to combine contexts"
     )]
-    pub context: Option<String>,
+    pub context: Vec<String>,
     #[arg(
         short,
         long,
         help = "print out the commands that would be run instead of executing them"
     )]
     pub dry_run: bool,
+    #[arg(
+        long,
+        value_name = "PATH",
+        value_parser = value_parser!(PathBuf),
+        help = "write the planned commands to a standalone shell script instead of running them, `-` prints it"
+    )]
+    pub emit_script: Option<PathBuf>,
+    #[arg(
+        long,
+        help = "pass command output straight to the terminal instead of prefixing each line"
+    )]
+    pub raw: bool,
+    #[arg(
+        short,
+        long,
+        help = "answer yes to confirmation prompts, required to run those tasks non-interactively"
+    )]
+    pub yes: bool,
+    #[arg(
+        short,
+        long,
+        help = "ask for missing task arguments on the terminal instead of failing"
+    )]
+    pub interactive: bool,
 }
@@ -1,61 +1,185 @@
+use crate::builtins::Builtin;
 use crate::cliargs::CliArgs;
 use crate::run::TaskBuilder;
-use crate::taskfile::Taskfile;
-use crate::utils::errors::{ErrWithMessage, UserFacingError};
+use crate::taskfile::{Bundle, Taskfile};
+use crate::utils::{
+    errors::{ErrWithMessage, UserFacingError},
+    prompt,
+};
 use clap::{ArgMatches, CommandFactory};
-use std::path::PathBuf;
+use std::{
+    ffi::OsString,
+    fs::File,
+    io::Read,
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+};
+
+// what a successful run did, decides the closing message
+#[derive(Debug, PartialEq)]
+pub(crate) enum RunOutcome {
+    Executed,
+    DryRun,
+    Builtin,
+    ScriptWritten(PathBuf),
+}
+
+// the file name tasker was started as, so a symlink like `deployctl -> tasker` is its own CLI
+fn program_name(argv0: Option<&OsString>) -> String {
+    let name = argv0
+        .and_then(|arg| Path::new(arg).file_name())
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    return match name.is_empty() {
+        true => "tasker".to_string(),
+        false => name,
+    };
+}
+
+// the Taskfile given as the first positional, the way the kernel passes an executable one
+// with a shebang line, other files are left to be task names
+fn script_path(matches: &ArgMatches) -> Option<PathBuf> {
+    let first = PathBuf::from(matches.get_many::<String>("task_info")?.next()?);
+    if !first.to_string_lossy().contains('/') || !first.is_file() {
+        return None;
+    }
+    let mut start = [0u8; 2];
+    File::open(&first).ok()?.read_exact(&mut start).ok()?;
+    return match &start == b"#!" {
+        true => Some(first),
+        false => None,
+    };
+}
+
+// `-c` when it exists, the global Taskfile otherwise
+fn config_path(matches: &ArgMatches) -> Result<String, UserFacingError> {
+    let expand = |path: &PathBuf| shellexpand::tilde(&path.to_string_lossy()).to_string();
+    let config_path = match matches.get_one::<PathBuf>("config_path").map(expand) {
+        Some(path) if Path::new(&path).exists() => path,
+        _ => match matches.get_one::<PathBuf>("global_config_path") {
+            Some(gfp) => expand(gfp),
+            _ => {
+                return Err(UserFacingError::TaskfileDoesNotExist(ErrWithMessage {
+                    code: "INVALID_TASKFILE_PATH".to_string(),
+                    messages: vec!["Taskfile does not exist".to_string()],
+                }))
+            }
+        },
+    };
+    Ok(config_path)
+}
+
+// the Taskfile of a CLI tasker is linked as, `<home>/.tasker/<name>/Taskfile`, when there is one
+fn named_taskfile(home: &str, name: &str) -> Option<String> {
+    let path = Path::new(home).join(".tasker").join(name).join("Taskfile");
+    return match path.is_file() {
+        true => Some(path.to_string_lossy().to_string()),
+        false => None,
+    };
+}
 
 pub(crate) struct EntryPoint {
     initial_arg_matches: ArgMatches,
+    // loaded up front to name the CLI after it, the one a built CLI carries or the one `-c`
+    // points at, None when it could not be loaded
+    taskfile: Option<Taskfile>,
+    // the name the CLI runs under
+    name: String,
 }
 impl EntryPoint {
     pub fn new(cli_input: Option<Vec<&str>>) -> Result<EntryPoint, UserFacingError> {
-        let matches = EntryPoint::get_matches(cli_input)?;
+        let mut args: Vec<OsString> = match &cli_input {
+            Some(args) => args.iter().map(OsString::from).collect(),
+            None => std::env::args_os().collect(),
+        };
+        let (cmd, name, taskfile) = EntryPoint::cli_command(&mut args)?;
+        let matches = EntryPoint::get_matches(cmd, args, cli_input.is_some())?;
         Ok(EntryPoint {
             initial_arg_matches: matches,
+            taskfile,
+            name,
         })
     }
-    fn get_matches(cli_input: Option<Vec<&str>>) -> Result<ArgMatches, UserFacingError> {
+    // the command line of the CLI, named after argv[0], the Taskfile run as a script or the
+    // bundle of a built CLI, with about, version and author from the Taskfile. A script is
+    // taken out of `args`
+    fn cli_command(
+        args: &mut Vec<OsString>,
+    ) -> Result<(clap::Command, String, Option<Taskfile>), UserFacingError> {
         let cmd = CliArgs::command();
-        return match cli_input {
-            Some(args) => match cmd.try_get_matches_from(args) {
+        if let Some(bundle) = Bundle::embedded() {
+            let taskfile = Taskfile::from_bundle(&bundle)?;
+            // the Taskfile is built in, there is no other one to point at
+            let cmd = taskfile
+                .apply_metadata(cmd)
+                .mut_arg("config_path", |arg| arg.hide(true))
+                .mut_arg("global_config_path", |arg| arg.hide(true));
+            return Ok((cmd, bundle.name, Some(taskfile)));
+        }
+        let mut name = program_name(args.first());
+        let mut cmd = cmd;
+        let home = shellexpand::tilde("~").to_string();
+        if let Some(bound) = named_taskfile(&home, &name).filter(|_| name != "tasker") {
+            // a name tasker is linked as can have its own Taskfile, `-c` still picks another one
+            cmd = cmd
+                .mut_arg("config_path", |arg| arg.default_value(bound.to_owned()))
+                .mut_arg("global_config_path", |arg| arg.default_value(bound));
+        }
+        // only looks for the Taskfile, mistakes on the command line surface in the real parse
+        let found = cmd
+            .to_owned()
+            .ignore_errors(true)
+            .disable_help_flag(true)
+            .disable_version_flag(true)
+            .try_get_matches_from(args.iter())
+            .ok();
+        let mut path = found.as_ref().and_then(|matches| config_path(matches).ok());
+        // `#!/usr/bin/env tasker` runs a Taskfile as `tasker ./ops.yaml deploy`
+        if let Some(script) = found.as_ref().and_then(script_path) {
+            name = program_name(Some(&script.to_owned().into_os_string()));
+            if let Some(index) = args.iter().skip(1).position(|arg| *arg == *script) {
+                args.remove(index + 1);
+            }
+            let script = script.to_string_lossy().to_string();
+            cmd = cmd.mut_arg("config_path", |arg| arg.default_value(script.to_owned()));
+            path = Some(script);
+        }
+        let taskfile = path
+            .and_then(|path| Taskfile::new(path).ok())
+            .map(|mut taskfile| {
+                taskfile.cli_name = Some(name.to_owned());
+                taskfile
+            });
+        let cmd = match &taskfile {
+            Some(taskfile) => taskfile.apply_metadata(cmd),
+            None => cmd.name(name.to_owned()).bin_name(name.to_owned()),
+        };
+        return Ok((cmd, name, taskfile));
+    }
+    fn get_matches(
+        cmd: clap::Command,
+        args: Vec<OsString>,
+        passed_in: bool,
+    ) -> Result<ArgMatches, UserFacingError> {
+        // args passed in by a caller get errors back instead of the process exiting
+        return match passed_in {
+            true => match cmd.try_get_matches_from(args) {
                 Ok(matches) => Ok(matches),
                 Err(e) => Err(UserFacingError::TaskfileDoesNotExist(ErrWithMessage {
                     code: "INVALID_TASKFILE_PATH".to_string(),
                     messages: vec![e.to_string()],
                 })),
             },
-            None => match cmd.try_get_matches() {
+            false => match cmd.try_get_matches_from(args) {
                 Ok(matches) => Ok(matches),
+                // --help and --version are not errors, print them the way clap normally would
+                Err(e) if !e.use_stderr() => e.exit(),
                 Err(e) => return Err(e.into()),
             },
         };
     }
     fn get_config_path(&self) -> Result<String, UserFacingError> {
-        let config_path = match self.initial_arg_matches.get_one::<PathBuf>("config_path") {
-            Some(fp) if fp.exists() => {
-                let path = fp.to_string_lossy().to_string();
-                shellexpand::tilde(&path).to_string()
-            }
-            _ => {
-                match self
-                    .initial_arg_matches
-                    .get_one::<PathBuf>("global_config_path")
-                {
-                    Some(gfp) => {
-                        let path = gfp.to_string_lossy().to_string();
-                        shellexpand::tilde(&path).to_string()
-                    }
-                    _ => {
-                        return Err(UserFacingError::TaskfileDoesNotExist(ErrWithMessage {
-                            code: "INVALID_TASKFILE_PATH".to_string(),
-                            messages: vec!["Taskfile does not exist".to_string()],
-                        }))
-                    }
-                }
-            }
-        };
-        Ok(config_path)
+        return config_path(&self.initial_arg_matches);
     }
     fn is_dry_run(&self) -> Result<bool, UserFacingError> {
         let dry_run = self.initial_arg_matches.get_one::<bool>("dry_run");
@@ -64,33 +188,91 @@ impl EntryPoint {
             _ => Ok(false),
         }
     }
-    pub fn run(&self) -> Result<bool, UserFacingError> {
-        let config_path = self.get_config_path()?;
-        let config = Taskfile::new(config_path)?;
+    fn get_task_info(&self) -> Vec<String> {
+        return match self.initial_arg_matches.get_many::<String>("task_info") {
+            Some(task_info) => task_info.cloned().collect(),
+            None => vec![],
+        };
+    }
+    fn write_script(path: &PathBuf, script: &str) -> Result<(), UserFacingError> {
+        if path.as_os_str() == "-" {
+            print!("{}", script);
+            return Ok(());
+        }
+        let written = std::fs::write(path, script)
+            .and_then(|_| std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o755)));
+        return written.map_err(|e| {
+            UserFacingError::TaskExecutionError(ErrWithMessage {
+                code: "WRITE_ERROR".to_string(),
+                messages: vec![format!("could not write `{}`: {}", path.display(), e)],
+            })
+        });
+    }
+    pub fn run(&self) -> Result<RunOutcome, UserFacingError> {
+        let task_info = self.get_task_info();
+        let loaded = match &self.taskfile {
+            Some(taskfile) => Ok(taskfile.to_owned()),
+            None => self
+                .get_config_path()
+                .and_then(|path| Ok(Taskfile::new(path)?)),
+        };
+        let config = match loaded {
+            Ok(config) => config,
+            Err(e) => match Builtin::without_taskfile(&task_info) {
+                Some(builtin) => {
+                    builtin.run(&task_info, None, &self.initial_arg_matches, &self.name)?;
+                    return Ok(RunOutcome::Builtin);
+                }
+                None => return Err(e),
+            },
+        };
+        if let Some(builtin) = Builtin::from_task_info(&task_info, &config) {
+            builtin.run(
+                &task_info,
+                Some(&config),
+                &self.initial_arg_matches,
+                &self.name,
+            )?;
+            return Ok(RunOutcome::Builtin);
+        }
         let mut builder = TaskBuilder::new(config);
         let dry_run = self.is_dry_run()?;
-        let runner = builder.create_task_runner(self.initial_arg_matches.to_owned())?;
+        let runner = builder
+            .create_task_runner(self.initial_arg_matches.to_owned())?
+            .with_signal_handling(true);
+        if let Some(path) = self.initial_arg_matches.get_one::<PathBuf>("emit_script") {
+            Self::write_script(path, &runner.render_script())?;
+            return Ok(RunOutcome::ScriptWritten(path.to_owned()));
+        }
         return match dry_run {
             true => {
                 runner.print_commands();
-                Ok(false)
+                Ok(RunOutcome::DryRun)
             }
             false => {
+                let assume_yes = self.initial_arg_matches.get_flag("yes");
+                runner.confirm(assume_yes, prompt::is_interactive())?;
                 runner.execute_tasks()?;
-                Ok(true)
+                Ok(RunOutcome::Executed)
             }
         };
     }
 }
 
-pub fn handle_result(result: Result<bool, UserFacingError>) {
+pub fn handle_result(result: Result<RunOutcome, UserFacingError>) {
     match result {
-        Ok(true) => {
+        Ok(RunOutcome::Executed) => {
             println!("Task completed successfully");
         }
-        Ok(false) => {
+        Ok(RunOutcome::DryRun) => {
             println!("Task completed successfully (dry run)");
         }
+        Ok(RunOutcome::Builtin) => {}
+        Ok(RunOutcome::ScriptWritten(path)) => {
+            if path.as_os_str() != "-" {
+                println!("Script written to {}", path.display());
+            }
+        }
         Err(e) => match e {
             UserFacingError::TaskfileDoesNotExist(e) => {
                 eprintln!("{}", e);
@@ -109,16 +291,33 @@ pub fn handle_result(result: Result<bool, UserFacingError>) {
                 std::process::exit(1);
             }
             UserFacingError::TaskDoesNotExist(_) => {
-                CliArgs::command().print_long_help().unwrap();
+                let mut args: Vec<OsString> = std::env::args_os().collect();
+                match EntryPoint::cli_command(&mut args) {
+                    Ok((mut cmd, _, _)) => cmd.print_long_help().unwrap(),
+                    Err(_) => CliArgs::command().print_long_help().unwrap(),
+                }
+                std::process::exit(1);
+            }
+            UserFacingError::InvalidContext(_) | UserFacingError::Aborted(_) => {
+                eprintln!("{}", e);
                 std::process::exit(1);
             }
+            UserFacingError::Interrupted(_, _) => {
+                eprintln!("{}", e);
+                std::process::exit(e.exit_code());
+            }
         },
     }
 }
 #[cfg(test)]
 mod integration_tests {
-    use crate::{cliargs::CliArgs, entrypoint::EntryPoint};
+    use crate::{
+        cliargs::CliArgs,
+        entrypoint::{config_path, named_taskfile, program_name, EntryPoint, RunOutcome},
+        taskfile::Taskfile,
+    };
     use clap::{CommandFactory, FromArgMatches};
+    use std::ffi::OsString;
 
     #[test]
     fn test_entry_point() {
@@ -134,6 +333,51 @@ mod integration_tests {
         assert!(result.is_ok())
     }
     #[test]
+    fn test_builtin_contexts() {
+        let ep =
+            EntryPoint::new(Some(vec!["tasker", "-c", "src/tests/Taskfile", "contexts"])).unwrap();
+        assert_eq!(ep.run().unwrap(), RunOutcome::Builtin);
+    }
+    #[test]
+    fn test_multiple_contexts() {
+        let ep = EntryPoint::new(Some(vec![
+            "tasker",
+            "-c",
+            "src/tests/Taskfile",
+            "-x",
+            "staging",
+            "-x",
+            "prod",
+            "--dry-run",
+            "greet",
+            "Peter",
+        ]))
+        .unwrap();
+        assert_eq!(ep.run().unwrap(), RunOutcome::DryRun);
+    }
+    #[test]
+    fn test_emit_script() {
+        let path = std::env::temp_dir().join(format!("tasker-emit-{}.sh", std::process::id()));
+        let ep = EntryPoint::new(Some(vec![
+            "tasker",
+            "-c",
+            "src/tests/Taskfile",
+            "--emit-script",
+            path.to_str().unwrap(),
+            "greet",
+            "Peter",
+        ]))
+        .unwrap();
+        assert_eq!(
+            ep.run().unwrap(),
+            RunOutcome::ScriptWritten(path.to_owned())
+        );
+        let output = std::process::Command::new(&path).output().unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert!(output.status.success());
+        assert!(String::from_utf8_lossy(&output.stdout).contains("Peter"));
+    }
+    #[test]
     fn test_from_arg_matches() {
         let initial_arg_matches = CliArgs::command().get_matches_from(vec![
             "tasker",
@@ -171,6 +415,8 @@ mod integration_tests {
                 "greet",
                 "Peter",
             ]),
+            taskfile: None,
+            name: "tasker".to_string(),
         };
         let result = ep.run();
         assert!(result.is_err())
@@ -186,6 +432,8 @@ mod integration_tests {
                 "greet",
                 "Peter",
             ]),
+            taskfile: None,
+            name: "tasker".to_string(),
         };
         assert!(ep.is_dry_run().unwrap());
         let ep = EntryPoint {
@@ -196,7 +444,131 @@ mod integration_tests {
                 "greet",
                 "Peter",
             ]),
+            taskfile: None,
+            name: "tasker".to_string(),
         };
         assert!(!ep.is_dry_run().unwrap());
     }
+    #[test]
+    fn test_embedded_taskfile() {
+        // a built CLI ignores `-c`, its Taskfile is built in
+        let ep = EntryPoint {
+            initial_arg_matches: CliArgs::command().get_matches_from(vec![
+                "opsctl",
+                "-c",
+                "src/tests/NotTaskfile",
+                "greet",
+                "Peter",
+            ]),
+            taskfile: Some(Taskfile::new("src/tests/Taskfile".to_string()).unwrap()),
+            name: "opsctl".to_string(),
+        };
+        assert_eq!(ep.run().unwrap(), RunOutcome::Executed);
+    }
+    #[test]
+    fn test_program_name() {
+        let ep = EntryPoint::new(Some(vec![
+            "/usr/local/bin/deployctl",
+            "-c",
+            "src/tests/Taskfile",
+            "greet",
+            "Peter",
+        ]))
+        .unwrap();
+        assert_eq!(ep.name, "deployctl");
+        assert_eq!(ep.taskfile.unwrap().cli_name(), "deployctl");
+        assert_eq!(program_name(None), "tasker");
+    }
+    #[test]
+    fn test_named_taskfile() {
+        let home = std::env::temp_dir().join(format!("tasker-home-{}", std::process::id()));
+        std::fs::create_dir_all(home.join(".tasker/deployctl")).unwrap();
+        std::fs::write(home.join(".tasker/deployctl/Taskfile"), "tasks: []\n").unwrap();
+        let home = home.to_string_lossy().to_string();
+        let found = named_taskfile(&home, "deployctl");
+        let missing = named_taskfile(&home, "opsctl");
+        let _ = std::fs::remove_dir_all(&home);
+        assert_eq!(found, Some(format!("{}/.tasker/deployctl/Taskfile", home)));
+        assert_eq!(missing, None);
+    }
+    #[test]
+    fn test_renamed_binary_uses_local_taskfile() {
+        // a copy or link without its own Taskfile keeps using ./Taskfile
+        let ep = EntryPoint::new(Some(vec![
+            "/usr/local/bin/tasker-renamed-without-taskfile",
+            "greet",
+        ]))
+        .unwrap();
+        assert_eq!(ep.name, "tasker-renamed-without-taskfile");
+        assert_eq!(config_path(&ep.initial_arg_matches).unwrap(), "Taskfile");
+        let taskfile = ep.taskfile.unwrap();
+        assert_eq!(taskfile.path.unwrap().to_string_lossy(), "Taskfile");
+        assert_eq!(
+            taskfile.cli_name.as_deref(),
+            Some("tasker-renamed-without-taskfile")
+        );
+    }
+    #[test]
+    fn test_config_path_expands_home() {
+        let matches = CliArgs::command().get_matches_from(["tasker", "-c", "~", "greet"]);
+        assert_eq!(
+            config_path(&matches).unwrap(),
+            shellexpand::tilde("~").to_string()
+        );
+        let matches =
+            CliArgs::command().get_matches_from(["tasker", "-c", "~/tasker-not-a-file", "greet"]);
+        assert_eq!(
+            config_path(&matches).unwrap(),
+            shellexpand::tilde("~/.tasker/Taskfile").to_string()
+        );
+    }
+    #[test]
+    fn test_executable_taskfile() {
+        let path = std::env::temp_dir().join(format!("tasker-ops-{}.yaml", std::process::id()));
+        let taskfile = std::fs::read_to_string("src/tests/Taskfile").unwrap();
+        std::fs::write(&path, format!("#!/usr/bin/env tasker\n{}", taskfile)).unwrap();
+        let script = path.to_string_lossy().to_string();
+        let ep = EntryPoint::new(Some(vec!["tasker", &script, "-d", "greet", "Peter"]));
+        // the same path again after the task is an arg, only the script itself is taken out
+        let repeated = EntryPoint::new(Some(vec!["tasker", &script, "greet", &script]));
+        let _ = std::fs::remove_file(&path);
+        let ep = ep.unwrap();
+        assert_eq!(ep.name, format!("tasker-ops-{}.yaml", std::process::id()));
+        assert!(ep.is_dry_run().unwrap());
+        assert_eq!(ep.get_task_info(), vec!["greet", "Peter"]);
+        assert_eq!(repeated.unwrap().get_task_info(), vec!["greet", &script]);
+    }
+    #[test]
+    fn test_executable_taskfile_relative_path() {
+        let name = format!("tasker-relative-{}.tasks", std::process::id());
+        let taskfile = std::fs::read_to_string("src/tests/Taskfile").unwrap();
+        std::fs::write(
+            format!("target/{}", name),
+            format!("#!/usr/bin/env tasker\n{}", taskfile),
+        )
+        .unwrap();
+        let script = format!("./target/{}", name);
+        let mut args: Vec<OsString> = ["tasker", &script, "greet", "Peter"]
+            .iter()
+            .map(OsString::from)
+            .collect();
+        let command = EntryPoint::cli_command(&mut args);
+        let ep = EntryPoint::new(Some(vec!["tasker", &script, "greet", "Peter"]));
+        let _ = std::fs::remove_file(format!("target/{}", name));
+        let (mut command, cli_name, taskfile) = command.unwrap();
+        let taskfile = taskfile.unwrap();
+        assert_eq!(args, vec!["tasker", "greet", "Peter"]);
+        assert_eq!(taskfile.path.as_ref().unwrap().to_string_lossy(), script);
+        // help, completions and docs go by the script's file name
+        assert_eq!(cli_name, name);
+        assert_eq!(taskfile.cli_name(), name);
+        assert_eq!(command.get_name(), name);
+        assert!(command
+            .render_usage()
+            .to_string()
+            .contains(&format!("Usage: {}", name)));
+        let ep = ep.unwrap();
+        assert_eq!(ep.name, name);
+        assert_eq!(ep.run().unwrap(), RunOutcome::Executed);
+    }
 }
@@ -0,0 +1,52 @@
+//! tasker as a library: load a Taskfile, plan a task with its args and contexts, look at
+//! the plan and run it, the same way the `tasker` binary does.
+//!
+//! [`TaskRunner::execute_tasks`] runs the plan without asking anything. Tasks with `confirm`
+//! and protected contexts are only asked about by [`TaskRunner::confirm`], so call it first.
+//! Signal handlers are only installed with [`TaskRunner::with_signal_handling`].
+//!
+//! ```no_run
+//! use tasker::{TaskBuilder, Taskfile};
+//!
+//! let taskfile = Taskfile::new("Taskfile".to_string())?;
+//! let mut builder = TaskBuilder::new(taskfile);
+//! let plan = builder.plan("deploy", &["v1.2".to_string()], &["prod".to_string()])?;
+//! for step in plan.all_steps() {
+//!     println!("{}", step.command);
+//! }
+//! let runner = builder.runner(plan, false);
+//! // confirmations are up to the caller, `true` answers them all with yes
+//! runner.confirm(true, false)?;
+//! runner.execute_tasks()?;
+//! # Ok::<(), Box<dyn std::error::Error>>(())
+//! ```
+
+// explicit returns are the house style
+#![allow(clippy::needless_return)]
+#![allow(clippy::module_inception)]
+use entrypoint::{handle_result, EntryPoint};
+
+mod builtins;
+mod cliargs;
+mod entrypoint;
+mod run;
+mod taskfile;
+mod utils;
+
+pub use run::errors::{ExecutionError, StepFailure};
+pub use run::{Layer, PlanItem, Step, TaskBuilder, TaskPlan, TaskRunner, VariableSource};
+pub use taskfile::{
+    Backoff, CmdArg, Location, RetryPolicy, RunMode, Shell, TaskStanza, Taskfile, TaskfileError,
+};
+pub use utils::errors::{ErrWithMessage, UserFacingError};
+pub use utils::secrets::Secrets;
+
+// what the `tasker` binary runs: reads the process arguments and exits on errors
+#[doc(hidden)]
+pub fn run_cli() {
+    let entrypoint = EntryPoint::new(None);
+    match entrypoint {
+        Ok(ep) => handle_result(ep.run()),
+        Err(e) => handle_result(Err(e)),
+    };
+}
@@ -0,0 +1,122 @@
+use super::TaskRunner;
+use crate::utils::{
+    errors::{ErrWithMessage, UserFacingError},
+    prompt,
+};
+
+fn aborted(code: &str, message: String) -> UserFacingError {
+    UserFacingError::Aborted(ErrWithMessage {
+        code: code.to_string(),
+        messages: vec![message],
+    })
+}
+
+impl TaskRunner {
+    // (task, question) for every task in the plan that asks to be confirmed
+    pub fn confirmations(&self) -> Vec<(String, String)> {
+        let mut plans = vec![&self.plan];
+        plans.extend(self.plan.sub_tasks());
+        plans
+            .iter()
+            .filter_map(|plan| {
+                let question = plan.confirm.as_ref()?;
+                Some((plan.name.to_owned(), question.to_owned()))
+            })
+            .collect()
+    }
+    fn accepted(answer: &str) -> bool {
+        matches!(answer.trim().to_lowercase().as_str(), "y" | "yes")
+    }
+    // shows what is about to run and asks before any dangerous task or protected context
+    pub fn confirm(&self, assume_yes: bool, interactive: bool) -> Result<(), UserFacingError> {
+        let confirmations = self.confirmations();
+        if assume_yes || (confirmations.is_empty() && self.protected_contexts.is_empty()) {
+            return Ok(());
+        }
+        if !interactive {
+            return Err(aborted(
+                "CONFIRMATION_REQUIRED",
+                format!(
+                    "task `{}` has to be confirmed but stdin is not a terminal, pass --yes to run it",
+                    self.plan.name
+                ),
+            ));
+        }
+        self.print_commands();
+        let read_answer = |question: String| {
+            prompt::read_line(&question)
+                .map_err(|e| aborted("ABORTED", format!("could not read the answer: {}", e)))
+        };
+        for (task, question) in confirmations {
+            let question = format!("{} [y/N] ", self.plan.secrets.mask(&question));
+            if !Self::accepted(&read_answer(question)?) {
+                return Err(aborted(
+                    "ABORTED",
+                    format!("task `{}` was not confirmed", task),
+                ));
+            }
+        }
+        for context in &self.protected_contexts {
+            let question = format!(
+                "context `{}` is protected, type its name to continue: ",
+                context
+            );
+            if read_answer(question)?.trim() != context {
+                return Err(aborted(
+                    "ABORTED",
+                    format!("protected context `{}` was not confirmed", context),
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::TaskRunner;
+    use crate::run::{PlanItem, TaskPlan};
+
+    #[test]
+    fn test_confirmations() {
+        let plan = TaskPlan {
+            name: "destroy".to_string(),
+            confirm: Some("Really drop the prod database?".to_string()),
+            items: vec![PlanItem::Task(TaskPlan {
+                name: "drop".to_string(),
+                confirm: Some("Drop it?".to_string()),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let runner = TaskRunner::new(plan, false);
+        assert_eq!(
+            runner.confirmations(),
+            vec![
+                (
+                    "destroy".to_string(),
+                    "Really drop the prod database?".to_string()
+                ),
+                ("drop".to_string(), "Drop it?".to_string())
+            ]
+        );
+        assert!(runner.confirm(false, false).is_err());
+        assert!(runner.confirm(true, false).is_ok());
+    }
+    #[test]
+    fn test_accepted() {
+        assert!(TaskRunner::accepted("y"));
+        assert!(TaskRunner::accepted(" YES "));
+        assert!(!TaskRunner::accepted(""));
+        assert!(!TaskRunner::accepted("nope"));
+    }
+    #[test]
+    fn test_protected_context_needs_confirmation() {
+        let runner = TaskRunner::new(TaskPlan::default(), false)
+            .with_protected_contexts(vec!["prod".to_string()]);
+        assert!(runner.confirm(false, false).is_err());
+        assert!(TaskRunner::new(TaskPlan::default(), false)
+            .confirm(false, false)
+            .is_ok());
+    }
+}
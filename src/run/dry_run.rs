@@ -0,0 +1,579 @@
+use super::{PlanItem, Step, TaskPlan, TaskRunner, STEP_MARKER_VAR};
+use crate::taskfile::{Backoff, RetryPolicy, RunMode, Shell};
+use std::time::Duration;
+
+const RETRY_HELPER: &str = r#"# retry_count delay_seconds fixed|exponential "exit codes" command...
+tasker_retry() {
+    tasker_count=$1 tasker_delay=$2 tasker_backoff=$3 tasker_only_on=$4
+    shift 4
+    tasker_attempt=0
+    while :; do
+        "$@" && return 0
+        tasker_code=$?
+        [ "$tasker_attempt" -ge "$tasker_count" ] && return "$tasker_code"
+        if [ -n "$tasker_only_on" ]; then
+            case " $tasker_only_on " in
+                *" $tasker_code "*) ;;
+                *) return "$tasker_code" ;;
+            esac
+        fi
+        tasker_attempt=$((tasker_attempt + 1))
+        echo "> retrying in ${tasker_delay}s ($tasker_attempt/$tasker_count)" >&2
+        sleep "$tasker_delay"
+        [ "$tasker_backoff" = exponential ] && tasker_delay=$((tasker_delay * 2))
+    done
+}
+"#;
+const TIMEOUT_HELPER: &str = r#"# seconds command..., runs without a limit where timeout(1) is missing
+tasker_timeout() {
+    if command -v timeout >/dev/null 2>&1; then
+        timeout "$@"
+    else
+        shift
+        "$@"
+    fi
+}
+"#;
+
+fn format_duration(duration: Duration) -> String {
+    match duration.subsec_millis() {
+        0 => format!("{}s", duration.as_secs()),
+        _ => format!("{}ms", duration.as_millis()),
+    }
+}
+
+// the settings of a step worth pointing out next to it
+fn step_options(step: &Step) -> Vec<String> {
+    let mut options = vec![];
+    if step.ignore_error {
+        options.push("ignore_error".to_string());
+    }
+    if let Some(retries) = &step.retries {
+        options.push(format!("retries: {}", retries.count));
+    }
+    if let Some(timeout) = step.timeout {
+        options.push(format!("timeout: {}", format_duration(timeout)));
+    }
+    if step.interactive {
+        options.push("interactive".to_string());
+    }
+    options
+}
+fn plan_options(plan: &TaskPlan) -> Vec<String> {
+    let mut options = vec![];
+    if plan.mode == RunMode::Steps {
+        options.push("steps".to_string());
+    }
+    if plan.strict {
+        options.push("strict".to_string());
+    }
+    if plan.ignore_error {
+        options.push("ignore_error".to_string());
+    }
+    options
+}
+fn with_options(label: String, options: Vec<String>) -> String {
+    match options.is_empty() {
+        true => label,
+        false => format!("{} [{}]", label, options.join(", ")),
+    }
+}
+
+pub(super) fn shell_quote(text: &str) -> String {
+    let safe = !text.is_empty()
+        && text
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "_-./=:,@%+".contains(c));
+    match safe {
+        true => text.to_string(),
+        false => format!("'{}'", text.replace('\'', "'\\''")),
+    }
+}
+pub(super) fn shell_invocation(shell: &Shell, script: &str) -> String {
+    let mut words = vec![shell_quote(&shell.program)];
+    words.extend(shell.args.iter().map(|arg| shell_quote(arg)));
+    words.push(shell_quote(script));
+    words.join(" ")
+}
+// the retry and timeout helpers a command runs through
+fn wrappers(retries: Option<&RetryPolicy>, timeout: Option<Duration>) -> String {
+    let mut wrappers = String::new();
+    if let Some(policy) = retries {
+        let only_on: Vec<String> = policy.only_on.iter().map(|code| code.to_string()).collect();
+        // sleep only takes whole seconds everywhere
+        let delay = policy.delay.as_millis().div_ceil(1000);
+        let backoff = match policy.backoff {
+            Backoff::Fixed => "fixed",
+            Backoff::Exponential => "exponential",
+        };
+        wrappers.push_str(&format!(
+            "tasker_retry {} {} {} {} ",
+            policy.count,
+            delay,
+            backoff,
+            shell_quote(&only_on.join(" "))
+        ));
+    }
+    if let Some(timeout) = timeout {
+        wrappers.push_str(&format!(
+            "tasker_timeout {} ",
+            timeout.as_millis().div_ceil(1000)
+        ));
+    }
+    wrappers
+}
+
+// turns a plan into shell functions, one per task
+pub(super) struct ScriptWriter {
+    functions: Vec<String>,
+    uses_retries: bool,
+    uses_timeouts: bool,
+    // handlers read the failure, so their own commands must not overwrite it
+    in_handler: bool,
+}
+impl ScriptWriter {
+    pub(super) fn new() -> Self {
+        ScriptWriter {
+            functions: vec![],
+            uses_retries: false,
+            uses_timeouts: false,
+            in_handler: false,
+        }
+    }
+    // the helpers the functions use followed by the functions
+    pub(super) fn definitions(&self) -> String {
+        let mut script = String::new();
+        if self.uses_retries {
+            script.push_str(&format!("{}\n", RETRY_HELPER));
+        }
+        if self.uses_timeouts {
+            script.push_str(&format!("{}\n", TIMEOUT_HELPER));
+        }
+        for function in &self.functions {
+            script.push_str(&format!("{}\n", function));
+        }
+        script
+    }
+    fn failure_line(task: &str, command: &str) -> String {
+        format!(
+            "failed_task={} failed_command={}",
+            shell_quote(task),
+            shell_quote(command)
+        )
+    }
+    fn step_lines(&mut self, step: &Step, on_error: &str) -> Vec<String> {
+        let script = match TaskRunner::strict_prelude(&step.shell, step.strict) {
+            Some(prelude) => format!("{}\n{}", prelude, step.command),
+            None => step.command.to_owned(),
+        };
+        self.uses_retries |= step.retries.is_some();
+        self.uses_timeouts |= step.timeout.is_some();
+        let on_error = match step.ignore_error {
+            true => "true",
+            false => on_error,
+        };
+        let mut lines = vec![];
+        if !self.in_handler {
+            lines.push(Self::failure_line(&step.task, &step.command));
+        }
+        lines.push(format!(
+            "{}{} || {}",
+            wrappers(step.retries.as_ref(), step.timeout),
+            shell_invocation(&step.shell, &script),
+            on_error
+        ));
+        lines
+    }
+    // lines running `items` in order, `on_error` decides what happens after a failure
+    fn item_lines(&mut self, items: &[PlanItem], on_error: &str) -> Vec<String> {
+        let mut lines = vec![];
+        for item in items {
+            match item {
+                PlanItem::Step(step) => lines.extend(self.step_lines(step, on_error)),
+                PlanItem::Task(plan) => {
+                    let function = self.write_plan(plan);
+                    let on_error = match plan.ignore_error {
+                        true => "true",
+                        false => on_error,
+                    };
+                    lines.push(format!("{} || {}", function, on_error));
+                }
+            }
+        }
+        lines
+    }
+    fn script_mode_lines(&mut self, plan: &TaskPlan) -> Vec<String> {
+        let mut lines = vec![];
+        lines.extend(TaskRunner::strict_prelude(&plan.shell, plan.strict));
+        lines.extend(TaskRunner::script_lines(&plan.items, &mut 0, false));
+        let script: String = lines.iter().map(|line| format!("{};\n", line)).collect();
+        let commands: Vec<String> = TaskPlan::flatten(&plan.items)
+            .iter()
+            .map(|step| step.command.to_owned())
+            .collect();
+        self.uses_retries |= plan.retries.is_some();
+        self.uses_timeouts |= plan.timeout.is_some();
+        let mut lines = vec![];
+        if !self.in_handler {
+            lines.push(Self::failure_line(&plan.name, &commands.join("; ")));
+        }
+        lines.push(format!(
+            "{}{} || return",
+            wrappers(plan.retries.as_ref(), plan.timeout),
+            shell_invocation(&plan.shell, &script)
+        ));
+        lines
+    }
+    fn function(name: &str, comment: &str, lines: Vec<String>) -> String {
+        let mut function = format!("# {}\n{}() {{\n", comment, name);
+        if lines.is_empty() {
+            function.push_str("    :\n");
+        }
+        for line in lines {
+            function.push_str(&format!("    {}\n", line));
+        }
+        function.push_str("}\n");
+        function
+    }
+    // writes the functions for a plan and its sub tasks, returns the name to call it by
+    pub(super) fn write_plan(&mut self, plan: &TaskPlan) -> String {
+        let id = self.functions.len();
+        self.functions.push(String::new());
+        let name = format!("tasker_task_{}", id);
+        let mut label = format!("task `{}`", plan.name);
+        if !plan.args.is_empty() {
+            label = format!("task `{} {}`", plan.name, plan.args.join(" "));
+        }
+        let steps = match plan.mode {
+            RunMode::Steps => self.item_lines(&plan.items, "return"),
+            RunMode::Script => self.script_mode_lines(plan),
+        };
+        let mut cleanup: Vec<&PlanItem> = vec![];
+        if plan.mode == RunMode::Script {
+            // sub tasks were folded into the script, so their cleanup is ours to run
+            for sub_task in plan.sub_tasks() {
+                cleanup.extend(&sub_task.finally);
+            }
+        }
+        cleanup.extend(&plan.finally);
+        if plan.on_fail.is_empty() && cleanup.is_empty() {
+            self.functions[id] = Self::function(&name, &label, steps);
+            return name;
+        }
+
+        let status = format!("tasker_status_{}", id);
+        let mut lines = vec![format!("{}_steps", name), format!("{}=$?", status)];
+        let in_handler = self.in_handler;
+        self.in_handler = true;
+        if !plan.on_fail.is_empty() {
+            let on_fail = self.item_lines(&plan.on_fail, "return");
+            self.functions.push(Self::function(
+                &format!("{}_on_fail", name),
+                &format!("on_fail of {}", label),
+                on_fail,
+            ));
+            lines.push(format!("if [ \"${}\" -ne 0 ]; then", status));
+            lines.push(format!("    exit_code=${}", status));
+            lines.push("    export failed_task failed_command exit_code".to_string());
+            lines.push(format!(
+                "    {}_on_fail || echo {} >&2",
+                name,
+                shell_quote(&format!("on_fail for task `{}` failed", plan.name))
+            ));
+            lines.push("fi".to_string());
+        }
+        // cleanup keeps going past failures and reports the first one
+        let cleanup_status = format!("tasker_cleanup_{}", id);
+        let cleanup: Vec<PlanItem> = cleanup.into_iter().cloned().collect();
+        lines.extend(self.item_lines(&cleanup, &format!(": \"${{{}:=$?}}\"", cleanup_status)));
+        self.in_handler = in_handler;
+        lines.push(format!(
+            "[ \"${}\" -eq 0 ] && {}=${{{}:-0}}",
+            status, status, cleanup_status
+        ));
+        lines.push(format!("return \"${}\"", status));
+        self.functions.push(Self::function(
+            &format!("{}_steps", name),
+            &format!("commands of {}", label),
+            steps,
+        ));
+        self.functions[id] = Self::function(&name, &label, lines);
+        name
+    }
+}
+
+// where the steps below a tree node run
+#[derive(Clone, Copy)]
+struct StepContext<'a> {
+    // the directory tasker runs in, steps do not change it for each other
+    cwd: &'a str,
+    // the shell of the script the steps are part of, when they run as one
+    script: Option<&'a Shell>,
+    // `finally` commands run one by one, whatever mode their task is in
+    cleanup: bool,
+    // `on_fail` commands get the failure in their environment
+    failure: bool,
+}
+impl<'a> StepContext<'a> {
+    fn enter(self, plan: &'a TaskPlan) -> Self {
+        match (self.script, plan.mode, self.cleanup) {
+            (None, RunMode::Script, false) => StepContext {
+                script: Some(&plan.shell),
+                ..self
+            },
+            _ => self,
+        }
+    }
+    fn describe(&self, step: &Step) -> String {
+        let mut env = String::from("inherited");
+        if self.failure {
+            env.push_str(" + failed_task, failed_command, exit_code");
+        }
+        match self.script {
+            // the builder only keeps tasks in script mode when every command uses its shell
+            Some(_) => format!(
+                "cwd: {} at the start of the script, shell: {} (one script), env: {} + {}",
+                self.cwd, step.shell, env, STEP_MARKER_VAR
+            ),
+            None => format!("cwd: {}, shell: {}, env: {}", self.cwd, step.shell, env),
+        }
+    }
+}
+
+impl TaskRunner {
+    fn tree_lines(
+        &self,
+        plan: &TaskPlan,
+        prefix: &str,
+        context: StepContext,
+        output: &mut Vec<String>,
+    ) {
+        // handlers show up as a node holding their commands
+        let mut children: Vec<(&str, Vec<&PlanItem>)> = vec![];
+        for item in &plan.items {
+            children.push(("", vec![item]));
+        }
+        for (label, items) in [("on_fail", &plan.on_fail), ("finally", &plan.finally)] {
+            if !items.is_empty() {
+                children.push((label, items.iter().collect()));
+            }
+        }
+        let count = children.len();
+        for (i, (label, items)) in children.into_iter().enumerate() {
+            let (branch, indent) = match i == count - 1 {
+                true => ("└── ", "    "),
+                false => ("├── ", "│   "),
+            };
+            let child_prefix = format!("{}{}", prefix, indent);
+            if !label.is_empty() {
+                output.push(format!("{}{}{}", prefix, branch, label));
+                let handler = TaskPlan {
+                    mode: RunMode::Steps,
+                    shell: plan.shell.to_owned(),
+                    items: items.into_iter().cloned().collect(),
+                    ..Default::default()
+                };
+                // handlers run after the script of their task, on their own
+                let handler_context = StepContext {
+                    script: None,
+                    cleanup: label == "finally",
+                    failure: label == "on_fail",
+                    ..context
+                };
+                self.tree_lines(&handler, &child_prefix, handler_context, output);
+                continue;
+            }
+            match items[0] {
+                PlanItem::Step(step) => {
+                    let command = self
+                        .plan
+                        .secrets
+                        .mask(&step.command)
+                        .replace('\n', "\n    ");
+                    let line = with_options(command, step_options(step));
+                    output.push(format!("{}{}{}", prefix, branch, line));
+                    output.push(format!("{}  {}", child_prefix, context.describe(step)));
+                }
+                PlanItem::Task(sub_plan) => {
+                    let mut label = format!("task: {}", sub_plan.name);
+                    if !sub_plan.args.is_empty() {
+                        label.push_str(&format!(" {}", sub_plan.args.join(" ")));
+                    }
+                    let mut label = self.plan.secrets.mask(&label);
+                    if sub_plan.shell != plan.shell {
+                        label.push_str(&format!(" ({})", sub_plan.shell));
+                    }
+                    let line = with_options(label, plan_options(sub_plan));
+                    output.push(format!("{}{}{}", prefix, branch, line));
+                    self.tree_lines(sub_plan, &child_prefix, context.enter(sub_plan), output);
+                }
+            }
+        }
+    }
+    // the plan as a tree of tasks, sub tasks and commands, each command with where it runs
+    pub fn render_tree(&self) -> String {
+        let cwd = match std::env::current_dir() {
+            Ok(cwd) => cwd.display().to_string(),
+            Err(_) => "?".to_string(),
+        };
+        let context = StepContext {
+            cwd: &cwd,
+            script: None,
+            cleanup: false,
+            failure: false,
+        };
+        let mut lines = vec![with_options(
+            self.plan.name.to_owned(),
+            plan_options(&self.plan),
+        )];
+        self.tree_lines(&self.plan, "", context.enter(&self.plan), &mut lines);
+        for (task, question) in self.confirmations() {
+            lines.push(format!(
+                "confirm ({}): {}",
+                task,
+                self.plan.secrets.mask(&question)
+            ));
+        }
+        for context in &self.protected_contexts {
+            lines.push(format!("protected context: {}", context));
+        }
+        lines.iter().map(|line| format!("{}\n", line)).collect()
+    }
+    pub fn print_commands(&self) {
+        print!("{}", self.render_tree());
+    }
+    // a POSIX shell script doing what running the plan would, secrets stay masked
+    pub fn render_script(&self) -> String {
+        let mut writer = ScriptWriter::new();
+        let entry = writer.write_plan(&self.plan);
+        let mut script = String::from("#!/bin/sh\n");
+        script.push_str(&format!(
+            "# `{}` as planned by tasker, run it from the directory tasker would run in\n",
+            self.plan.name
+        ));
+        if !self.plan.secrets.is_empty() {
+            script.push_str("# secret values are written as ***, fill them in before running it\n");
+        }
+        for (task, question) in self.confirmations() {
+            script.push_str(&format!(
+                "# asks before running ({}): {}\n",
+                task,
+                question.replace('\n', " ")
+            ));
+        }
+        script.push('\n');
+        script.push_str(&writer.definitions());
+        script.push_str(&format!("{}\n", entry));
+        self.plan.secrets.mask(&script)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::run::{PlanItem, Step, TaskPlan, TaskRunner};
+    use crate::taskfile::{Backoff, RetryPolicy, RunMode};
+    use std::{process::Command, time::Duration};
+
+    fn step(task: &str, command: &str) -> PlanItem {
+        PlanItem::Step(Step {
+            task: task.to_string(),
+            command: command.to_string(),
+            template: command.to_string(),
+            ..Default::default()
+        })
+    }
+    fn plan() -> TaskPlan {
+        TaskPlan {
+            name: "deploy".to_string(),
+            mode: RunMode::Steps,
+            items: vec![
+                step("deploy", "echo one"),
+                PlanItem::Task(TaskPlan {
+                    name: "build".to_string(),
+                    args: vec!["1.2".to_string()],
+                    mode: RunMode::Script,
+                    items: vec![step("build", "cd /tmp"), step("build", "echo 'it''s' two")],
+                    ..Default::default()
+                }),
+                PlanItem::Step(Step {
+                    task: "deploy".to_string(),
+                    command: "exit 3".to_string(),
+                    ignore_error: true,
+                    retries: Some(RetryPolicy {
+                        count: 1,
+                        backoff: Backoff::Fixed,
+                        delay: Duration::ZERO,
+                        only_on: vec![],
+                    }),
+                    timeout: Some(Duration::from_secs(30)),
+                    ..Default::default()
+                }),
+                step("deploy", "exit 4"),
+            ],
+            on_fail: vec![step(
+                "deploy",
+                "echo \"$failed_command failed with $exit_code\"",
+            )],
+            finally: vec![step("deploy", "echo cleanup")],
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_render_tree() {
+        let mut plan = plan();
+        plan.items.insert(
+            1,
+            PlanItem::Step(Step {
+                task: "deploy".to_string(),
+                command: "echo $BASH_VERSION".to_string(),
+                shell: serde_yaml::from_str("bash -c").unwrap(),
+                ..Default::default()
+            }),
+        );
+        let cwd = std::env::current_dir().unwrap().display().to_string();
+        let tree = TaskRunner::new(plan, false)
+            .render_tree()
+            .replace(&cwd, "CWD");
+        let steps = "cwd: CWD, shell: sh -c, env: inherited";
+        let script = "cwd: CWD at the start of the script, shell: sh -c (one script), env: inherited + TASKER_STEP_MARKER";
+        assert_eq!(
+            tree,
+            format!(
+                "deploy [steps]
+├── echo one
+│     {steps}
+├── echo $BASH_VERSION
+│     cwd: CWD, shell: bash -c, env: inherited
+├── task: build 1.2
+│   ├── cd /tmp
+│   │     {script}
+│   └── echo 'it''s' two
+│         {script}
+├── exit 3 [ignore_error, retries: 1, timeout: 30s]
+│     {steps}
+├── exit 4
+│     {steps}
+├── on_fail
+│   └── echo \"$failed_command failed with $exit_code\"
+│         cwd: CWD, shell: sh -c, env: inherited + failed_task, failed_command, exit_code
+└── finally
+    └── echo cleanup
+          {steps}
+"
+            )
+        );
+    }
+    #[test]
+    fn test_render_script_runs() {
+        let script = TaskRunner::new(plan(), false).render_script();
+        assert!(script.starts_with("#!/bin/sh\n"));
+        assert!(script.contains("tasker_retry() {"));
+        let output = Command::new("sh").arg("-c").arg(&script).output().unwrap();
+        assert_eq!(output.status.code(), Some(4));
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "one\nits two\nexit 4 failed with 4\ncleanup\n"
+        );
+    }
+}
@@ -1,13 +1,153 @@
-use crate::utils::errors::ErrWithMessage;
+use super::{plan::Step, signals};
+use crate::utils::{errors::ErrWithMessage, secrets::Secrets};
+use std::time::Duration;
+
+// details of a step that exited unsuccessfully, handed to `on_fail` commands
+#[derive(Debug, Clone)]
+pub struct StepFailure {
+    pub task: String,
+    pub command: String,
+    // the command as written in the Taskfile, before variables were filled in
+    pub template: String,
+    // `task:` commands that led to the step, outermost first
+    pub called_from: Vec<String>,
+    pub exit_code: i32,
+}
+impl StepFailure {
+    pub fn new(step: &Step) -> Self {
+        StepFailure {
+            task: step.task.to_owned(),
+            command: step.command.to_owned(),
+            template: step.template.to_owned(),
+            called_from: step.called_from.to_owned(),
+            exit_code: 0,
+        }
+    }
+    fn masked(self, secrets: &Secrets) -> Self {
+        StepFailure {
+            command: secrets.mask(&self.command),
+            template: secrets.mask(&self.template),
+            called_from: self
+                .called_from
+                .iter()
+                .map(|caller| secrets.mask(caller))
+                .collect(),
+            ..self
+        }
+    }
+    // messages are shown last to first, so these come before the summary
+    fn origin_messages(&self) -> Vec<String> {
+        let mut messages = vec![];
+        if !self.template.is_empty() && self.template != self.command {
+            messages.push(format!("written as `{}`", self.template));
+        }
+        if !self.called_from.is_empty() {
+            messages.push(format!(
+                "reached through `task: {}`",
+                self.called_from.join("` > `task: ")
+            ));
+        }
+        messages
+    }
+    pub fn to_err_with_message(&self) -> ErrWithMessage {
+        let mut messages = self.origin_messages();
+        messages.push(format!(
+            "`{}` in task `{}` exited with status {}",
+            self.command, self.task, self.exit_code
+        ));
+        ErrWithMessage {
+            code: "NON_ZERO_EXIT".to_string(),
+            messages,
+        }
+    }
+}
+
+// a step that was killed for running longer than its timeout
+#[derive(Debug, Clone)]
+pub struct StepTimeout {
+    pub failure: StepFailure,
+    pub elapsed: Duration,
+}
+impl StepTimeout {
+    pub fn to_err_with_message(&self) -> ErrWithMessage {
+        let mut messages = self.failure.origin_messages();
+        messages.push(format!(
+            "`{}` in task `{}` timed out after {:.1}s",
+            self.failure.command,
+            self.failure.task,
+            self.elapsed.as_secs_f64()
+        ));
+        ErrWithMessage {
+            code: "TIMEOUT".to_string(),
+            messages,
+        }
+    }
+}
 
 #[derive(Debug)]
 pub enum ExecutionError {
     CommandFailed(ErrWithMessage),
+    StepFailed(StepFailure),
+    TimedOut(StepTimeout),
+    // holds the signal that stopped the run
+    Interrupted(i32),
 }
+impl ExecutionError {
+    // the step behind the error, when a step is what failed
+    pub fn failure(&self) -> Option<&StepFailure> {
+        match self {
+            ExecutionError::StepFailed(failure) => Some(failure),
+            ExecutionError::TimedOut(timeout) => Some(&timeout.failure),
+            _ => None,
+        }
+    }
+    // the error with every secret value in it replaced with ***
+    pub fn masked(self, secrets: &Secrets) -> Self {
+        match self {
+            ExecutionError::CommandFailed(e) => ExecutionError::CommandFailed(ErrWithMessage {
+                messages: e.messages.iter().map(|m| secrets.mask(m)).collect(),
+                ..e
+            }),
+            ExecutionError::StepFailed(failure) => {
+                ExecutionError::StepFailed(failure.masked(secrets))
+            }
+            ExecutionError::TimedOut(timeout) => ExecutionError::TimedOut(StepTimeout {
+                failure: timeout.failure.masked(secrets),
+                ..timeout
+            }),
+            ExecutionError::Interrupted(signal) => ExecutionError::Interrupted(signal),
+        }
+    }
+    pub fn interrupted_message(signal: i32) -> ErrWithMessage {
+        ErrWithMessage {
+            code: "INTERRUPTED".to_string(),
+            messages: vec![format!(
+                "received {}, stopped after running cleanup",
+                signals::name(signal)
+            )],
+        }
+    }
+}
+impl std::error::Error for ExecutionError {}
 impl std::fmt::Display for ExecutionError {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         match self {
             ExecutionError::CommandFailed(e) => write!(f, "Command failed to execute:\n    {}", e),
+            ExecutionError::StepFailed(e) => {
+                write!(
+                    f,
+                    "Command failed to execute:\n    {}",
+                    e.to_err_with_message()
+                )
+            }
+            ExecutionError::TimedOut(e) => {
+                write!(f, "Command timed out:\n    {}", e.to_err_with_message())
+            }
+            ExecutionError::Interrupted(signal) => write!(
+                f,
+                "Execution interrupted:\n    {}",
+                Self::interrupted_message(*signal)
+            ),
         }
     }
 }
@@ -72,5 +212,59 @@ mod test {
         );
     }
     #[test]
+    fn test_display_step_failed() {
+        let error = ExecutionError::StepFailed(StepFailure {
+            task: "greet".to_string(),
+            command: "exit 3".to_string(),
+            template: "exit 3".to_string(),
+            called_from: vec![],
+            exit_code: 3,
+        });
+        assert_eq!(
+            error.to_string(),
+            "Command failed to execute:\n    \u{1b}[31mNON_ZERO_EXIT\u{1b}[0m: `exit 3` in task `greet` exited with status 3"
+        );
+    }
+    #[test]
+    fn test_display_timed_out() {
+        let error = ExecutionError::TimedOut(StepTimeout {
+            failure: StepFailure {
+                task: "greet".to_string(),
+                command: "sleep 10".to_string(),
+                template: "sleep 10".to_string(),
+                called_from: vec![],
+                exit_code: 124,
+            },
+            elapsed: Duration::from_millis(1500),
+        });
+        assert_eq!(
+            error.to_string(),
+            "Command timed out:\n    \u{1b}[31mTIMEOUT\u{1b}[0m: `sleep 10` in task `greet` timed out after 1.5s"
+        );
+        assert_eq!(error.failure().unwrap().exit_code, 124);
+    }
+    #[test]
+    fn test_display_interrupted() {
+        let error = ExecutionError::Interrupted(libc::SIGTERM);
+        assert_eq!(
+            error.to_string(),
+            "Execution interrupted:\n    \u{1b}[31mINTERRUPTED\u{1b}[0m: received SIGTERM, stopped after running cleanup"
+        );
+    }
+    #[test]
+    fn test_display_step_failed_origin() {
+        let error = ExecutionError::StepFailed(StepFailure {
+            task: "greet".to_string(),
+            command: "echo $UNSET".to_string(),
+            template: "echo ${greeting}".to_string(),
+            called_from: vec!["release 1.2".to_string(), "greet".to_string()],
+            exit_code: 2,
+        });
+        assert_eq!(
+            error.to_string(),
+            "Command failed to execute:\n    \u{1b}[31mNON_ZERO_EXIT\u{1b}[0m: `echo $UNSET` in task `greet` exited with status 2\n>    reached through `task: release 1.2` > `task: greet`\n>    written as `echo ${greeting}`"
+        );
+    }
+    #[test]
     fn test_h() {}
 }
@@ -0,0 +1,802 @@
+use super::{
+    dry_run::{shell_invocation, shell_quote, ScriptWriter},
+    PlanItem, Step, TaskBuilder, TaskPlan, TaskRunner,
+};
+use crate::{
+    taskfile::{RunMode, SecretSource, Shell},
+    utils::errors::UserFacingError,
+};
+use std::collections::HashMap;
+
+// stands in for a variable whose value is only known when the exported file runs, each
+// format writes it its own way
+fn placeholder(name: &str) -> String {
+    return format!("\u{1}{}\u{2}", name);
+}
+
+// placeholders in `text`, in order of first use
+fn placeholders(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for part in text.split('\u{1}').skip(1) {
+        if let Some((name, _)) = part.split_once('\u{2}') {
+            if !names.iter().any(|known| known == name) {
+                names.push(name.to_string());
+            }
+        }
+    }
+    return names;
+}
+
+fn fill_placeholders(text: &str, fill: impl Fn(&str) -> String) -> String {
+    let mut output = String::new();
+    let mut parts = text.split('\u{1}');
+    output.push_str(parts.next().unwrap_or_default());
+    for part in parts {
+        match part.split_once('\u{2}') {
+            Some((name, rest)) => {
+                output.push_str(&fill(name));
+                output.push_str(rest);
+            }
+            None => output.push_str(part),
+        }
+    }
+    return output;
+}
+
+// shell and make variable names can only hold letters, digits and underscores
+fn variable_name(name: &str) -> String {
+    return name
+        .chars()
+        .map(|c| match c.is_ascii_alphanumeric() {
+            true => c,
+            false => '_',
+        })
+        .collect();
+}
+
+// a variable the exported file fills in when it runs
+#[derive(Debug, Clone, PartialEq)]
+pub struct ExportVariable {
+    pub name: String,
+    // an arg of the task, taken from the command line
+    pub arg: bool,
+    pub default: Option<String>,
+    // a `secrets:` entry, read the same way tasker reads it
+    pub secret: Option<SecretSource>,
+}
+impl ExportVariable {
+    pub fn env_var(&self) -> String {
+        return format!("TASKER_{}", variable_name(&self.name).to_uppercase());
+    }
+}
+
+// a task expanded for export, with args, secrets and secret context values left open
+#[derive(Debug, Clone)]
+pub struct ExportedTask {
+    pub name: String,
+    pub description: Option<String>,
+    pub variables: Vec<ExportVariable>,
+    pub plan: TaskPlan,
+    // questions to ask before running, from `confirm` of the task and its sub-tasks
+    pub confirm: Vec<String>,
+    // protected contexts, their name has to be typed before running
+    pub protected: Vec<String>,
+}
+
+// everything the task runs, handlers included, as text to look for placeholders in
+fn plan_text(plan: &TaskPlan) -> String {
+    let mut text: Vec<String> = plan
+        .all_steps()
+        .iter()
+        .map(|step| step.command.to_owned())
+        .collect();
+    let mut plans = vec![plan];
+    plans.extend(plan.sub_tasks());
+    text.extend(plans.iter().filter_map(|plan| plan.confirm.to_owned()));
+    for items in [&plan.items, &plan.on_fail, &plan.finally] {
+        for item in items {
+            match item {
+                PlanItem::Task(call) if call.unresolved => {
+                    text.push(call.name.to_owned());
+                    text.extend(call.args.to_owned());
+                }
+                PlanItem::Task(sub_plan) => text.push(plan_text(sub_plan)),
+                PlanItem::Step(_) => {}
+            }
+        }
+    }
+    return text.join("\n");
+}
+
+// `plan` with each `task:` call whose task is only known when the exported file runs
+// replaced by a step handing the call back to the file, `dispatch` writes that command
+fn dispatch_calls(
+    plan: &TaskPlan,
+    dispatch: &dyn Fn(&TaskPlan) -> String,
+    dispatched: &mut bool,
+) -> TaskPlan {
+    let mut plan = plan.to_owned();
+    for items in [&mut plan.items, &mut plan.on_fail, &mut plan.finally] {
+        for item in items.iter_mut() {
+            let PlanItem::Task(sub_plan) = item else {
+                continue;
+            };
+            *item = match sub_plan.unresolved {
+                true => {
+                    *dispatched = true;
+                    let command = dispatch(sub_plan);
+                    PlanItem::Step(Step {
+                        task: plan.name.to_owned(),
+                        template: command.to_owned(),
+                        command,
+                        ignore_error: sub_plan.ignore_error,
+                        ..Default::default()
+                    })
+                }
+                false => PlanItem::Task(dispatch_calls(sub_plan, dispatch, dispatched)),
+            };
+        }
+    }
+    return plan;
+}
+
+impl TaskBuilder {
+    // every task the contexts allow, expanded the way a run would expand it
+    pub fn export_tasks(
+        &self,
+        context_names: Vec<String>,
+    ) -> Result<(Vec<String>, Vec<ExportedTask>), UserFacingError> {
+        let context_names = match context_names.is_empty() {
+            true => self.config.default_context.iter().cloned().collect(),
+            false => context_names,
+        };
+        if let Some(name) = context_names
+            .iter()
+            .find(|name| !self.config.contexts.contains_key(*name))
+        {
+            return Err(self.unknown_context_error(name));
+        }
+        let context = self.config.get_context(&context_names);
+        let secret_keys = self.config.context_secret_keys(&context_names);
+        let protected: Vec<String> = context_names
+            .iter()
+            .filter(|name| self.config.is_context_protected(name))
+            .cloned()
+            .collect();
+        let mut tasks = vec![];
+        for task in &self.config.tasks {
+            // tasks the contexts rule out are left out, the same as tasker would refuse them
+            if self
+                .select_contexts(&task.name, context_names.to_owned())
+                .is_err()
+            {
+                continue;
+            }
+            // same order as a run: secrets, contexts, then the task's own args
+            let mut variables = HashMap::new();
+            for name in self.config.secrets.keys() {
+                variables.insert(name.to_owned(), placeholder(name));
+            }
+            for (key, value) in &context {
+                let value = match secret_keys.contains(key) {
+                    true => placeholder(key),
+                    false => value.to_owned(),
+                };
+                variables.insert(key.to_owned(), value);
+            }
+            for arg in task.get_command_args() {
+                variables.insert(arg.get_name().to_string(), placeholder(arg.get_name()));
+            }
+            // a call naming its task through an arg is kept and dispatched when the file runs
+            let plan = self.expand_task(task.to_owned(), variables, true)?;
+            // every arg is taken on the command line, used or not
+            let mut names: Vec<String> = task
+                .get_command_args()
+                .iter()
+                .map(|arg| arg.get_name().to_string())
+                .collect();
+            for name in placeholders(&plan_text(&plan)) {
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+            let exported = names
+                .into_iter()
+                .map(|name| {
+                    let arg = task
+                        .get_command_args()
+                        .iter()
+                        .find(|arg| arg.get_name() == name);
+                    let default = match context.get(&name) {
+                        Some(value) if !secret_keys.contains(&name) => Some(value.to_owned()),
+                        _ => arg.and_then(|arg| arg.get_default().map(String::from)),
+                    };
+                    let secret = match arg.is_some() || context.contains_key(&name) {
+                        true => None,
+                        false => self.config.secrets.get(&name).cloned(),
+                    };
+                    ExportVariable {
+                        arg: arg.is_some(),
+                        name,
+                        default,
+                        secret,
+                    }
+                })
+                .collect();
+            let runner = TaskRunner::new(plan.to_owned(), false);
+            let confirm = runner
+                .confirmations()
+                .into_iter()
+                .map(|(_, question)| question)
+                .collect();
+            tasks.push(ExportedTask {
+                name: task.name.to_owned(),
+                description: task.description.to_owned(),
+                variables: exported,
+                plan,
+                confirm,
+                protected: protected.to_owned(),
+            });
+        }
+        return Ok((context_names, tasks));
+    }
+}
+
+// `greet <first_name> [last_name]`
+fn usage(task: &ExportedTask, required: &str, optional: &str) -> String {
+    let mut usage = task.name.to_owned();
+    for variable in task.variables.iter().filter(|variable| variable.arg) {
+        match &variable.default {
+            Some(_) => usage.push_str(&optional.replace("NAME", &variable.name)),
+            None => usage.push_str(&required.replace("NAME", &variable.name)),
+        }
+    }
+    return usage;
+}
+
+fn header(tool: &str, context_names: &[String]) -> String {
+    let mut header = format!(
+        "# generated by `tasker export --to {}`, edit the Taskfile instead\n",
+        tool
+    );
+    if !context_names.is_empty() {
+        header.push_str(&format!("# contexts: {}\n", context_names.join(", ")));
+    }
+    return header;
+}
+
+const SH_HELPERS: &str = r#"tasker_fail() {
+    echo "$1" >&2
+    exit 2
+}
+# TASKER_YES=1 answers yes, without a terminal the answer is no
+tasker_confirm() {
+    [ -n "${TASKER_YES:-}" ] && return 0
+    if [ ! -t 0 ]; then
+        echo "$1 (set TASKER_YES=1 to run without a terminal)" >&2
+        return 1
+    fi
+    printf '%s [y/N] ' "$1" >&2
+    read -r tasker_answer
+    case "$tasker_answer" in
+        y | Y | yes | YES) return 0 ;;
+        *) return 1 ;;
+    esac
+}
+# a protected context has to be typed out, like tasker asks for it
+tasker_protected() {
+    [ -n "${TASKER_YES:-}" ] && return 0
+    if [ ! -t 0 ]; then
+        echo "context \`$1\` is protected (set TASKER_YES=1 to run without a terminal)" >&2
+        return 1
+    fi
+    printf 'context `%s` is protected, type its name to continue: ' "$1" >&2
+    read -r tasker_answer
+    [ "$tasker_answer" = "$1" ]
+}
+"#;
+
+// lines filling in the variables of a task from the command line, TASKER_<NAME> and defaults
+fn sh_arguments(task: &ExportedTask) -> Vec<String> {
+    let mut lines = vec![];
+    for variable in &task.variables {
+        let name = variable_name(&variable.name);
+        let env = variable.env_var();
+        let initial = match (&variable.secret, &variable.default) {
+            (Some(SecretSource::Env(env)), _) => format!("${{{}-}}", variable_name(env)),
+            (Some(SecretSource::File(path)), _) => {
+                let path = match path.strip_prefix("~/") {
+                    Some(rest) => format!("\"$HOME\"/{}", shell_quote(rest)),
+                    None => shell_quote(path),
+                };
+                format!("$(cat {})", path)
+            }
+            (None, Some(default)) => shell_quote(default),
+            (None, None) => "''".to_string(),
+        };
+        lines.push(format!("{}={}", name, initial));
+        if variable.secret.is_none() {
+            lines.push(format!("[ -n \"${{{}+x}}\" ] && {}=${}", env, name, env));
+        }
+    }
+    for variable in task.variables.iter().filter(|variable| variable.arg) {
+        let name = variable_name(&variable.name);
+        // `-` leaves an arg to its env var or default, like it does for tasker
+        lines.push(format!(
+            "if [ $# -gt 0 ]; then [ \"$1\" = - ] || {}=$1; shift; fi",
+            name
+        ));
+    }
+    lines.push(format!(
+        "[ $# -eq 0 ] || tasker_fail {}",
+        shell_quote(&format!("{}: unexpected argument", task.name))
+    ));
+    for variable in &task.variables {
+        if variable.default.is_some() || variable.secret.is_some() {
+            continue;
+        }
+        let name = variable_name(&variable.name);
+        let message = match variable.arg {
+            true => format!("{}: missing {}", task.name, variable.name),
+            false => format!("{}: {} has to be set", task.name, variable.env_var()),
+        };
+        lines.push(format!(
+            "[ -n \"${}\" ] || tasker_fail {}",
+            name,
+            shell_quote(&message)
+        ));
+    }
+    if !task.variables.is_empty() {
+        let names: Vec<String> = task
+            .variables
+            .iter()
+            .map(|variable| variable_name(&variable.name))
+            .collect();
+        // commands run in their own shell and read the values from the environment
+        lines.push(format!("export {}", names.join(" ")));
+    }
+    for name in &task.protected {
+        lines.push(format!("tasker_protected {} || exit 1", shell_quote(name)));
+    }
+    for question in &task.confirm {
+        // placeholders are left out of the quotes to be expanded
+        let question = fill_placeholders(&shell_quote(question), |name| {
+            format!("'\"${}\"'", variable_name(name))
+        });
+        lines.push(format!("tasker_confirm {} || exit 1", question));
+    }
+    return lines;
+}
+
+// a shell word for `text`, with its placeholders left out of the quotes to be expanded
+fn sh_word(text: &str) -> String {
+    let mut parts = text.split('\u{1}');
+    let mut word = String::new();
+    let first = parts.next().unwrap_or_default();
+    if !first.is_empty() || !text.contains('\u{1}') {
+        word.push_str(&shell_quote(first));
+    }
+    for part in parts {
+        let (name, rest) = part.split_once('\u{2}').unwrap_or(("", part));
+        if !name.is_empty() {
+            word.push_str(&format!("\"{}\"", placeholder(name)));
+        }
+        if !rest.is_empty() {
+            word.push_str(&shell_quote(rest));
+        }
+    }
+    return word;
+}
+
+// a POSIX shell script with a function per task and a `case "$1"` picking one
+pub fn render_sh(tasks: &[ExportedTask], context_names: &[String]) -> String {
+    let mut writer = ScriptWriter::new();
+    let mut dispatched = false;
+    let dispatch = |call: &TaskPlan| {
+        let mut words = vec!["sh \"$TASKER_SELF\"".to_string(), sh_word(&call.name)];
+        words.extend(call.args.iter().map(|arg| sh_word(arg)));
+        words.join(" ")
+    };
+    let entries: Vec<String> = tasks
+        .iter()
+        .map(|task| writer.write_plan(&dispatch_calls(&task.plan, &dispatch, &mut dispatched)))
+        .collect();
+    let mut script = String::from("#!/bin/sh\n");
+    script.push_str(&header("sh", context_names));
+    script.push_str("# usage: <script> <task> [args], args can also be set as TASKER_<NAME>\n\n");
+    script.push_str(SH_HELPERS);
+    if dispatched {
+        // `task:` calls only known when the script runs hand the call back to the script
+        script.push_str("TASKER_SELF=$(cd \"$(dirname \"$0\")\" && pwd)/$(basename \"$0\")\n");
+        script.push_str("export TASKER_SELF\n");
+    }
+    script.push('\n');
+    script.push_str(&fill_placeholders(&writer.definitions(), |name| {
+        format!("${{{}}}", variable_name(name))
+    }));
+    script.push_str("tasker_usage() {\n    cat <<'EOF'\nusage: <script> <task> [args]\n\ntasks:\n");
+    for task in tasks {
+        let usage = usage(task, " <NAME>", " [NAME]");
+        match &task.description {
+            Some(description) => script.push_str(&format!("  {}\n      {}\n", usage, description)),
+            None => script.push_str(&format!("  {}\n", usage)),
+        }
+    }
+    script.push_str("EOF\n}\n\ncase \"${1:-}\" in\n");
+    for (task, entry) in tasks.iter().zip(entries) {
+        script.push_str(&format!(
+            "    {})\n        shift\n",
+            shell_quote(&task.name)
+        ));
+        for line in sh_arguments(task) {
+            script.push_str(&format!("        {}\n", line));
+        }
+        script.push_str(&format!("        {}\n        ;;\n", entry));
+    }
+    script.push_str("    \"\" | -h | --help | help)\n        tasker_usage\n        ;;\n");
+    script.push_str("    *)\n        tasker_usage >&2\n        tasker_fail \"unknown task: $1\"\n        ;;\nesac\n");
+    return script;
+}
+
+// `$` is make's, a literal one is written twice
+fn make_escape(text: &str) -> String {
+    return fill_placeholders(&text.replace('$', "$$"), |name| {
+        format!("$({})", variable_name(name))
+    });
+}
+
+// a recipe line handing `script` to `shell`, multi-line scripts are passed through printf
+// since recipe lines can not hold a newline
+fn make_command(shell: &Shell, script: &str) -> String {
+    if !script.contains('\n') && *shell == Shell::default() {
+        return make_escape(script);
+    }
+    if !script.contains('\n') {
+        return make_escape(&shell_invocation(shell, script));
+    }
+    let encoded = script.replace('\\', "\\\\").replace('\n', "\\n");
+    let mut words = vec![shell_quote(&shell.program)];
+    words.extend(shell.args.iter().map(|arg| shell_quote(arg)));
+    words.push(format!("\"$(printf '%b' {})\"", shell_quote(&encoded)));
+    return make_escape(&words.join(" "));
+}
+
+fn make_lines(plan: &TaskPlan, lines: &mut Vec<String>, skipped: &mut Vec<&'static str>) {
+    if plan.mode == RunMode::Script {
+        let mut script: Vec<String> = vec![];
+        script.extend(TaskRunner::strict_prelude(&plan.shell, plan.strict));
+        script.extend(TaskRunner::script_lines(&plan.items, &mut 0, false));
+        let prefix = if plan.ignore_error { "-" } else { "" };
+        lines.push(format!(
+            "{}{}",
+            prefix,
+            make_command(&plan.shell, &script.join("\n"))
+        ));
+    } else {
+        for item in &plan.items {
+            match item {
+                PlanItem::Step(step) => {
+                    let script = match TaskRunner::strict_prelude(&step.shell, step.strict) {
+                        Some(prelude) => format!("{}\n{}", prelude, step.command),
+                        None => step.command.to_owned(),
+                    };
+                    let prefix = if step.ignore_error || plan.ignore_error {
+                        "-"
+                    } else {
+                        ""
+                    };
+                    lines.push(format!("{}{}", prefix, make_command(&step.shell, &script)));
+                    if step.retries.is_some() {
+                        skipped.push("retries");
+                    }
+                    if step.timeout.is_some() {
+                        skipped.push("timeouts");
+                    }
+                }
+                PlanItem::Task(sub_plan) => make_lines(sub_plan, lines, skipped),
+            }
+        }
+    }
+    if !plan.on_fail.is_empty() {
+        skipped.push("on_fail");
+    }
+    if !plan.finally.is_empty() {
+        skipped.push("finally");
+    }
+    if plan.retries.is_some() {
+        skipped.push("retries");
+    }
+    if plan.timeout.is_some() {
+        skipped.push("timeouts");
+    }
+}
+
+fn make_value(value: &str) -> String {
+    return value.replace('$', "$$").replace('#', "\\#");
+}
+
+// a recipe command running the target `call` names with make, its args are handed over as
+// the variables of whichever task that turns out to be
+fn make_dispatch(call: &TaskPlan, tasks: &[ExportedTask]) -> String {
+    let make = format!(
+        "{} -f {}",
+        placeholder("MAKE"),
+        placeholder("TASKER_MAKEFILE")
+    );
+    let mut cases = vec![];
+    for task in tasks {
+        let mut words = vec![make.to_owned(), shell_quote(&task.name)];
+        let args = task.variables.iter().filter(|variable| variable.arg);
+        for (variable, value) in args.zip(&call.args) {
+            words.push(shell_quote(&format!(
+                "{}={}",
+                variable_name(&variable.name),
+                value
+            )));
+        }
+        cases.push(format!(
+            "{}) {} ;;",
+            shell_quote(&task.name),
+            words.join(" ")
+        ));
+    }
+    let name = shell_quote(&call.name);
+    cases.push(format!(
+        "*) echo {}{} >&2; exit 2 ;;",
+        shell_quote("unknown task: "),
+        name
+    ));
+    return format!("case {} in {} esac", name, cases.join(" "));
+}
+
+// a Makefile with a phony target per task, args are make variables: `make greet name=x`
+pub fn render_makefile(tasks: &[ExportedTask], context_names: &[String]) -> String {
+    let mut output = header("make", context_names);
+    let mut dispatched = false;
+    let dispatch = |call: &TaskPlan| make_dispatch(call, tasks);
+    let plans: Vec<TaskPlan> = tasks
+        .iter()
+        .map(|task| dispatch_calls(&task.plan, &dispatch, &mut dispatched))
+        .collect();
+    if dispatched {
+        // `task:` calls only known when make runs go through make again
+        output.push_str("\nTASKER_MAKEFILE := $(abspath $(firstword $(MAKEFILE_LIST)))\n");
+    }
+    let names: Vec<String> = tasks.iter().map(|task| task.name.to_owned()).collect();
+    let help = !names.iter().any(|name| name == "help");
+    let mut phony = names.to_owned();
+    if help {
+        phony.insert(0, "help".to_string());
+        output.push_str("\n.DEFAULT_GOAL := help\n");
+    }
+    output.push_str(&format!(".PHONY: {}\n", phony.join(" ")));
+    if help {
+        output.push_str("\nhelp:\n\t@echo 'tasks:'\n");
+        for task in tasks {
+            let usage = usage(task, " NAME=...", " [NAME=...]");
+            let line = match &task.description {
+                Some(description) => format!("  {}  {}", usage, description),
+                None => format!("  {}", usage),
+            };
+            output.push_str(&format!("\t@echo {}\n", make_escape(&shell_quote(&line))));
+        }
+    }
+    for (task, plan) in tasks.iter().zip(&plans) {
+        output.push('\n');
+        if let Some(description) = &task.description {
+            output.push_str(&format!("# {}\n", description));
+        }
+        let mut lines = vec![];
+        let mut skipped = vec![];
+        make_lines(plan, &mut lines, &mut skipped);
+        let mut seen = vec![];
+        skipped.retain(|feature| match seen.contains(feature) {
+            true => false,
+            false => {
+                seen.push(*feature);
+                true
+            }
+        });
+        if !skipped.is_empty() {
+            output.push_str(&format!(
+                "# not exported: {}, use `tasker export --to sh` to keep them\n",
+                skipped.join(", ")
+            ));
+        }
+        for variable in &task.variables {
+            let name = variable_name(&variable.name);
+            let value = match (&variable.secret, &variable.default) {
+                (Some(SecretSource::Env(env)), _) => format!("$({})", variable_name(env)),
+                (Some(SecretSource::File(path)), _) => {
+                    format!("$(shell cat {})", make_value(&shell_quote(path)))
+                }
+                (None, default) => format!(
+                    "$(or $({}),{})",
+                    variable.env_var(),
+                    make_value(default.as_deref().unwrap_or_default())
+                ),
+            };
+            output.push_str(&format!("{}: {} ?= {}\n", task.name, name, value));
+        }
+        output.push_str(&format!("{}:\n", task.name));
+        for variable in &task.variables {
+            if variable.default.is_some() || variable.secret.is_some() {
+                continue;
+            }
+            let message = format!(
+                "{}: {} is missing, e.g. make {} {}=...",
+                task.name,
+                variable.name,
+                task.name,
+                variable_name(&variable.name)
+            );
+            output.push_str(&format!(
+                "\t@[ -n \"$({})\" ] || {{ echo {} >&2; exit 2; }}\n",
+                variable_name(&variable.name),
+                make_escape(&shell_quote(&message))
+            ));
+        }
+        for name in &task.protected {
+            output.push_str(&format!(
+                "\t@[ -n \"$$TASKER_YES\" ] || {{ printf 'context `%s` is protected, type its name to continue: ' {}; read a; [ \"$$a\" = {} ] || exit 1; }}\n",
+                make_escape(&shell_quote(name)),
+                make_escape(&shell_quote(name))
+            ));
+        }
+        for question in &task.confirm {
+            let question = shell_quote(question);
+            output.push_str(&format!(
+                "\t@[ -n \"$$TASKER_YES\" ] || {{ printf '%s [y/N] ' {}; read a; case \"$$a\" in y|Y|yes|YES) ;; *) exit 1 ;; esac; }}\n",
+                make_escape(&question)
+            ));
+        }
+        for line in lines {
+            output.push_str(&format!("\t{}\n", line));
+        }
+    }
+    return output;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{fill_placeholders, placeholder, placeholders, render_makefile, render_sh};
+    use crate::{
+        run::TaskBuilder, taskfile::Taskfile, utils::test_helpers::test_helpers::load_from_string,
+    };
+    use std::process::Command;
+
+    fn dispatching_tasks() -> TaskBuilder {
+        let yaml = r#"
+        tasks:
+          - name: greet
+            commands:
+              - shell: echo hi ${who}
+            args:
+              - {name: who, type: string}
+          - name: run
+            commands:
+              - task: ${target} ${who}
+            args:
+              - {name: target, type: string}
+              - {name: who, type: string, default: you}
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        return TaskBuilder::new(taskfile);
+    }
+
+    #[test]
+    fn test_placeholders() {
+        let text = format!(
+            "echo {} {} {}",
+            placeholder("a"),
+            placeholder("b"),
+            placeholder("a")
+        );
+        assert_eq!(placeholders(&text), vec!["a", "b"]);
+        assert_eq!(
+            fill_placeholders(&text, |name| format!("${{{}}}", name)),
+            "echo ${a} ${b} ${a}"
+        );
+    }
+    #[test]
+    fn test_export_tasks() {
+        let builder = TaskBuilder::new(load_from_string());
+        let (contexts, tasks) = builder.export_tasks(vec!["test".to_string()]).unwrap();
+        assert_eq!(contexts, vec!["test"]);
+        let task = tasks.iter().find(|task| task.name == "test-cmd").unwrap();
+        let names: Vec<&str> = task.variables.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, vec!["required_arg", "default_arg"]);
+        assert_eq!(task.variables[1].default.as_deref(), Some("default"));
+        assert!(builder.export_tasks(vec!["nope".to_string()]).is_err());
+    }
+    #[test]
+    fn test_render_sh_runs() {
+        let builder = TaskBuilder::new(load_from_string());
+        let (contexts, tasks) = builder.export_tasks(vec![]).unwrap();
+        let script = render_sh(&tasks, &contexts);
+        let run = |args: &[&str]| {
+            Command::new("sh")
+                .arg("-c")
+                .arg(&script)
+                .arg("tasks.sh")
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        let output = run(&["test-cmd", "Peter"]);
+        assert!(output.status.success());
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "Hello Peter default\n"
+        );
+        let output = run(&["test-cmd", "Peter", "-"]);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "Hello Peter default\n"
+        );
+        let output = run(&["test-cmd"]);
+        assert_eq!(output.status.code(), Some(2));
+        assert!(String::from_utf8_lossy(&output.stderr).contains("test-cmd: missing required_arg"));
+        assert!(String::from_utf8_lossy(&run(&[]).stdout)
+            .contains("test-cmd <required_arg> [default_arg]"));
+    }
+    #[test]
+    fn test_render_makefile() {
+        let builder = TaskBuilder::new(load_from_string());
+        let (contexts, tasks) = builder.export_tasks(vec![]).unwrap();
+        let makefile = render_makefile(&tasks, &contexts);
+        assert!(makefile.contains(
+            "test-cmd: default_arg ?= $(or $(TASKER_DEFAULT_ARG),default)\ntest-cmd:\n\t@[ -n \"$(required_arg)\" ] || "
+        ));
+        assert!(makefile.contains("\techo Hello $(required_arg) $(default_arg)\n"));
+        let path = std::env::temp_dir().join(format!("tasker-export-{}.mk", std::process::id()));
+        std::fs::write(&path, &makefile).unwrap();
+        let output = Command::new("make")
+            .args(["-s", "-f"])
+            .arg(&path)
+            .args(["test-cmd", "required_arg=Peter"])
+            .output()
+            .unwrap();
+        let _ = std::fs::remove_file(&path);
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "Hello Peter default\n"
+        );
+    }
+    #[test]
+    fn test_export_unresolved_call() {
+        let (contexts, tasks) = dispatching_tasks().export_tasks(vec![]).unwrap();
+        let dir = std::env::temp_dir().join(format!("tasker-dispatch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("tasks.sh"), render_sh(&tasks, &contexts)).unwrap();
+        std::fs::write(dir.join("tasks.mk"), render_makefile(&tasks, &contexts)).unwrap();
+        let sh = |args: &[&str]| {
+            Command::new("sh")
+                .arg(dir.join("tasks.sh"))
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        let make = |args: &[&str]| {
+            Command::new("make")
+                .args(["-s", "-f"])
+                .arg(dir.join("tasks.mk"))
+                .args(args)
+                .output()
+                .unwrap()
+        };
+        let outputs = [
+            sh(&["run", "greet", "Bob"]),
+            sh(&["run", "nope"]),
+            make(&["run", "target=greet"]),
+            make(&["run", "target=nope"]),
+        ];
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(String::from_utf8_lossy(&outputs[0].stdout), "hi Bob\n");
+        assert!(!outputs[1].status.success());
+        assert!(String::from_utf8_lossy(&outputs[1].stderr).contains("unknown task: nope"));
+        assert_eq!(String::from_utf8_lossy(&outputs[2].stdout), "hi you\n");
+        assert!(!outputs[3].status.success());
+        assert!(String::from_utf8_lossy(&outputs[3].stderr).contains("unknown task: nope"));
+    }
+}
@@ -0,0 +1,88 @@
+use super::TaskBuilder;
+use crate::{taskfile::CmdArg, utils::prompt};
+use std::{env, io};
+
+// a numbered pick from the list, or the value itself
+fn resolve_choice(answer: &str, choices: &[String]) -> String {
+    let answer = answer.trim();
+    return match answer.parse::<usize>() {
+        Ok(number) if number >= 1 && number <= choices.len() => choices[number - 1].to_owned(),
+        _ => answer.to_string(),
+    };
+}
+
+// keeps asking until the value passes the arg's type and choices
+fn ask_for_arg(arg: &CmdArg, question: &str) -> io::Result<String> {
+    for (i, choice) in arg.choices.iter().enumerate() {
+        eprintln!("  {}) {}", i + 1, choice);
+    }
+    loop {
+        let question = match question.ends_with(['?', ':']) {
+            true => format!("{} ", question),
+            false => format!("{}: ", question),
+        };
+        let answer = match arg.secret {
+            true => prompt::read_secret(&question)?,
+            false => prompt::read_line(&question)?,
+        };
+        let answer = resolve_choice(&answer, &arg.choices);
+        if answer.is_empty() {
+            continue;
+        }
+        match arg.validate(&answer) {
+            Ok(_) => return Ok(answer),
+            Err(e) => eprintln!("{}", e),
+        }
+    }
+}
+
+impl TaskBuilder {
+    // fills in required args left off the command line by asking for them, gives up when one
+    // of them can not be prompted for
+    pub(super) fn prompt_for_missing_args(
+        &self,
+        raw_args: &[String],
+        interactive: bool,
+    ) -> Option<Vec<String>> {
+        let task = self.config.get_task_by_name(raw_args.first()?)?;
+        let mut args = raw_args.to_vec();
+        for arg in task.get_command_args().iter().skip(raw_args.len() - 1) {
+            let from_env = env::var(arg.env_var()).is_ok();
+            // args are positional, so anything before a prompted arg needs a value too
+            let value = match (arg.get_default(), from_env) {
+                (_, true) => "-".to_string(),
+                (Some(default), false) => default.to_string(),
+                (None, false) => ask_for_arg(arg, &arg.prompt_question(interactive)?).ok()?,
+            };
+            args.push(value);
+        }
+        return Some(args);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::resolve_choice;
+    use crate::run::TaskBuilder;
+    use crate::utils::test_helpers::test_helpers::load_from_string;
+
+    #[test]
+    fn test_resolve_choice() {
+        let choices = vec!["staging".to_string(), "prod".to_string()];
+        assert_eq!(resolve_choice("2", &choices), "prod");
+        assert_eq!(resolve_choice(" staging ", &choices), "staging");
+        assert_eq!(resolve_choice("3", &choices), "3");
+    }
+    #[test]
+    fn test_prompt_for_missing_args_needs_opt_in() {
+        let builder = TaskBuilder::new(load_from_string());
+        let raw_args = vec!["test-cmd".to_string()];
+        assert_eq!(builder.prompt_for_missing_args(&raw_args, false), None);
+        // nothing is missing, so nothing is asked
+        let raw_args = vec!["test-cmd".to_string(), "Foo".to_string()];
+        assert_eq!(
+            builder.prompt_for_missing_args(&raw_args, false).unwrap(),
+            vec!["test-cmd", "Foo", "default"]
+        );
+    }
+}
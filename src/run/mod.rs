@@ -1,105 +1,415 @@
+mod confirm;
+mod dry_run;
 pub mod errors;
-use self::errors::ExecutionError;
+mod export;
+mod missing_args;
+mod plan;
+mod process;
+mod signals;
+mod variables;
+use self::errors::{ExecutionError, StepFailure, StepTimeout};
+pub use self::export::{render_makefile, render_sh};
+pub use self::plan::{PlanItem, Step, TaskPlan};
+use self::process::{call_command, Finished, OutputMode, POLL_INTERVAL};
+pub use self::variables::{Layer, VariableSource};
 use crate::{
-    taskfile::{CommandTypes, TaskStanza, Taskfile},
+    builtins::Builtin,
+    taskfile::{CommandTypes, RetryPolicy, RunMode, Shell, TaskCmd, TaskStanza, Taskfile},
     utils::{
         errors::{ErrWithMessage, UserFacingError},
         iters::upsert_into_hash_map,
-        strings::split_exclude_quotes,
+        prompt,
+        secrets::Secrets,
+        strings::{closest_match, split_exclude_quotes},
     },
 };
-use clap::ArgMatches;
-use std::collections::HashMap;
+use clap::{parser::ValueSource, ArgMatches};
+use libc::SIGINT;
+use std::collections::{BTreeMap, HashMap};
 use std::{
-    io::{BufRead, BufReader},
-    process::{Command, Stdio},
+    fs,
+    os::unix::process::ExitStatusExt,
+    path::PathBuf,
+    process::{Command, ExitStatus},
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
 };
 
+// same status `timeout(1)` reports for a command it had to kill
+const TIMEOUT_EXIT_CODE: i32 = 124;
+// not every sh has pipefail (dash only gained it recently), so only turn it on where it exists
+const STRICT_PRELUDE: &str = "set -eu; (set -o pipefail) 2>/dev/null && set -o pipefail";
+// scripts write the index of the step they are about to run to the file named here
+const STEP_MARKER_VAR: &str = "TASKER_STEP_MARKER";
+static MARKER_COUNT: AtomicUsize = AtomicUsize::new(0);
+
 pub struct TaskRunner {
-    commands: Vec<String>,
+    plan: TaskPlan,
+    raw_output: bool,
+    // selected contexts that need their name typed before running
+    protected_contexts: Vec<String>,
+    // catch SIGINT and SIGTERM for the whole process while running
+    handle_signals: bool,
 }
 impl TaskRunner {
-    pub fn new(commands: Vec<String>) -> Self {
-        Self { commands }
-    }
-    pub fn call_command(mut command: Command) -> Result<(), ExecutionError> {
-        let cmd = command
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()?;
-        let output = cmd.wait_with_output()?;
-        let mut stdout_reader = BufReader::new(output.stdout.as_slice()).lines();
-        let mut stderr_reader = BufReader::new(output.stderr.as_slice()).lines();
-
+    pub fn new(plan: TaskPlan, raw_output: bool) -> Self {
+        Self {
+            plan,
+            raw_output,
+            protected_contexts: vec![],
+            handle_signals: false,
+        }
+    }
+    pub fn with_protected_contexts(mut self, protected_contexts: Vec<String>) -> Self {
+        self.protected_contexts = protected_contexts;
+        self
+    }
+    // lets tasker catch SIGINT and SIGTERM, so `finally` commands still run on Ctrl-C, which
+    // replaces the handlers of the program for good, so only opt in when it has none
+    pub fn with_signal_handling(mut self, handle_signals: bool) -> Self {
+        self.handle_signals = handle_signals;
+        self
+    }
+    fn output_mode(&self, interactive: bool) -> OutputMode {
+        if interactive {
+            return OutputMode::Interactive;
+        }
+        // output with secrets in it has to go through masking, which needs the prefixes
+        if !self.plan.secrets.is_empty() {
+            return OutputMode::Prefixed;
+        }
+        // there is nothing to tell apart when only one step runs, so skip the prefixes
+        let single_step = self.plan.steps().len() == 1;
+        match self.raw_output || single_step {
+            true => OutputMode::Raw,
+            false => OutputMode::Prefixed,
+        }
+    }
+    fn exit_code(status: ExitStatus) -> i32 {
+        // processes killed by a signal have no code, report them the way shells do
+        match status.code() {
+            Some(code) => code,
+            None => 128 + status.signal().unwrap_or_default(),
+        }
+    }
+    fn interrupted_error() -> ExecutionError {
+        ExecutionError::Interrupted(signals::received().unwrap_or(SIGINT))
+    }
+    fn parse_strings_into_single_command(shell: &Shell, commands: Vec<String>) -> Command {
+        let mut base_cmd = Command::new(&shell.program);
+        base_cmd.args(&shell.args);
+        let mut base_script = String::new();
+        for cmd in commands {
+            base_script.push_str(&format!("{};\n", cmd));
+        }
+        base_cmd.arg(base_script);
+        return base_cmd;
+    }
+    fn strict_prelude(shell: &Shell, strict: bool) -> Option<String> {
+        match strict && shell.is_posix() {
+            true => Some(STRICT_PRELUDE.to_string()),
+            false => None,
+        }
+    }
+    fn parse_string_into_command(shell: &Shell, command: &str) -> Command {
+        let mut base_cmd = Command::new(&shell.program);
+        base_cmd.args(&shell.args).arg(command);
+        return base_cmd;
+    }
+    // lines of the single script used by tasks in script mode, `index` counts the steps
+    // so markers can be left in front of them
+    fn script_lines(items: &[PlanItem], index: &mut usize, markers: bool) -> Vec<String> {
+        let mut lines = vec![];
+        for item in items {
+            if let PlanItem::Step(_) = item {
+                if markers {
+                    lines.push(format!(
+                        "printf '%s\\n' {} > \"${}\"",
+                        index, STEP_MARKER_VAR
+                    ));
+                }
+                *index += 1;
+            }
+            match item {
+                PlanItem::Step(step) if step.ignore_error => {
+                    lines.push(format!("{{ {}; }} || true", step.command))
+                }
+                PlanItem::Step(step) => lines.push(step.command.to_owned()),
+                PlanItem::Task(plan) if plan.ignore_error => {
+                    let mut block = String::from("{\n");
+                    for line in Self::script_lines(&plan.items, index, markers) {
+                        block.push_str(&format!("{};\n", line));
+                    }
+                    block.push_str("} || true");
+                    lines.push(block);
+                }
+                PlanItem::Task(plan) => {
+                    lines.extend(Self::script_lines(&plan.items, index, markers))
+                }
+            }
+        }
+        lines
+    }
+    // a scratch file for the step markers of one script run
+    fn marker_path() -> PathBuf {
+        let count = MARKER_COUNT.fetch_add(1, Ordering::SeqCst);
+        std::env::temp_dir().join(format!("tasker-{}-{}.step", std::process::id(), count))
+    }
+    // the step a script was running when it stopped, going by the last marker it wrote
+    fn marked_step<'a>(plan: &'a TaskPlan, marker: &PathBuf) -> Option<&'a Step> {
+        let index: usize = fs::read_to_string(marker).ok()?.trim().parse().ok()?;
+        plan.steps().get(index).copied()
+    }
+    fn check_finished(failure: StepFailure, finished: Finished) -> Result<(), ExecutionError> {
+        match finished {
+            Finished::Exited(status) if status.success() => Ok(()),
+            Finished::Exited(status) => Err(ExecutionError::StepFailed(StepFailure {
+                exit_code: Self::exit_code(status),
+                ..failure
+            })),
+            Finished::TimedOut(elapsed) => Err(ExecutionError::TimedOut(StepTimeout {
+                failure: StepFailure {
+                    exit_code: TIMEOUT_EXIT_CODE,
+                    ..failure
+                },
+                elapsed,
+            })),
+            Finished::Killed => Err(Self::interrupted_error()),
+        }
+    }
+    fn sleep_unless_interrupted(duration: Duration) {
+        let started = Instant::now();
+        while started.elapsed() < duration && !signals::interrupted() {
+            thread::sleep(POLL_INTERVAL.min(duration - started.elapsed()));
+        }
+    }
+    // runs `attempt` until it succeeds or the retry policy gives up
+    fn with_retries(
+        &self,
+        command: &str,
+        retries: Option<&RetryPolicy>,
+        attempt: impl Fn() -> Result<(), ExecutionError>,
+    ) -> Result<(), ExecutionError> {
+        let mut retried = 0;
         loop {
-            let stdout_line = stdout_reader.next();
-            let stderr_line = stderr_reader.next();
-            if stderr_line.is_none() && stdout_line.is_none() {
-                break;
+            let result = attempt();
+            let policy = match (&result, retries) {
+                (Err(e), Some(policy)) if retried < policy.count && !signals::interrupted() => {
+                    policy
+                }
+                _ => return result,
             };
-            if stdout_line.is_some() {
-                match stdout_line.unwrap() {
-                    Ok(line) => {
-                        println!("\x1b[32m>\x1b[0m {}", line);
-                    }
-                    Err(e) => {
-                        println!("Error reading stdout: {}", e);
-                        break;
-                    }
+            let retryable = match &result {
+                Err(ExecutionError::StepFailed(failure)) => {
+                    policy.retries_exit_code(failure.exit_code)
                 }
+                Err(ExecutionError::TimedOut(_)) => policy.only_on.is_empty(),
+                _ => false,
+            };
+            if !retryable {
+                return result;
             }
-            if stderr_line.is_some() {
-                if !output.status.success() {
-                    match stderr_line.unwrap() {
-                        Ok(line) => {
-                            println!("\x1b[31m>\x1b[0m {}", line);
-                        }
-                        Err(e) => {
-                            println!("Error reading stderr: {}", e);
-                            break;
-                        }
-                    }
+            let delay = policy.delay_for(retried);
+            retried += 1;
+            println!(
+                "\x1b[33m>\x1b[0m `{}` failed, retrying in {:.1}s ({}/{})",
+                self.plan.secrets.mask(command),
+                delay.as_secs_f64(),
+                retried,
+                policy.count
+            );
+            Self::sleep_unless_interrupted(delay);
+        }
+    }
+    fn run_step(&self, step: &Step) -> Result<(), ExecutionError> {
+        let script = match Self::strict_prelude(&step.shell, step.strict) {
+            Some(prelude) => format!("{}\n{}", prelude, step.command),
+            None => step.command.to_owned(),
+        };
+        self.with_retries(&step.command, step.retries.as_ref(), || {
+            let mut command = Self::parse_string_into_command(&step.shell, &script);
+            command.envs(step.env.to_owned());
+            let output = self.output_mode(step.interactive);
+            let finished = call_command(command, step.timeout, output, &self.plan.secrets)?;
+            Self::check_finished(StepFailure::new(step), finished)
+        })
+    }
+    fn run_script(&self, plan: &TaskPlan) -> Result<(), ExecutionError> {
+        let markers = plan.shell.is_posix();
+        let mut lines = vec![];
+        lines.extend(Self::strict_prelude(&plan.shell, plan.strict));
+        lines.extend(Self::script_lines(&plan.items, &mut 0, markers));
+        let script = TaskPlan::flatten(&plan.items)
+            .iter()
+            .map(|step| step.command.to_owned())
+            .collect::<Vec<_>>()
+            .join("; ");
+        let marker = Self::marker_path();
+        let result = self.with_retries(&script, plan.retries.as_ref(), || {
+            let _ = fs::remove_file(&marker);
+            let mut command =
+                Self::parse_strings_into_single_command(&plan.shell, lines.to_owned());
+            command.env(STEP_MARKER_VAR, &marker);
+            for step in TaskPlan::flatten(&plan.items) {
+                command.envs(step.env.to_owned());
+            }
+            let output = self.output_mode(plan.interactive);
+            let finished = call_command(command, plan.timeout, output, &self.plan.secrets)?;
+            let failure = match Self::marked_step(plan, &marker) {
+                Some(step) => StepFailure::new(step),
+                None => StepFailure {
+                    task: plan.name.to_owned(),
+                    command: script.to_owned(),
+                    template: script.to_owned(),
+                    called_from: vec![],
+                    exit_code: 0,
+                },
+            };
+            Self::check_finished(failure, finished)
+        });
+        let _ = fs::remove_file(&marker);
+        result
+    }
+    fn run_items(&self, items: &[PlanItem]) -> Result<(), ExecutionError> {
+        for item in items {
+            if signals::interrupted() {
+                return Err(Self::interrupted_error());
+            }
+            let (result, ignore_error) = match item {
+                PlanItem::Step(step) => (self.run_step(step), step.ignore_error),
+                PlanItem::Task(plan) => (self.run_plan(plan), plan.ignore_error),
+            };
+            if signals::interrupted() {
+                return Err(Self::interrupted_error());
+            }
+            match result {
+                Err(e) if ignore_error && e.failure().is_some() => {
+                    println!(
+                        "\x1b[33m>\x1b[0m ignoring failure:\n    {}",
+                        self.plan.secrets.mask(&e.to_string())
+                    );
                 }
+                Err(e) => return Err(e),
+                Ok(_) => {}
             }
         }
-
         Ok(())
     }
-    fn parse_strings_into_single_command(&self) -> Command {
-        let commands = self.commands.clone();
-        let mut base_cmd = Command::new("sh");
-        base_cmd.arg("-c");
-        let mut base_script = String::new();
-        for cmd in commands {
-            base_script.push_str(&format!("{};\n", cmd));
+    // cleanup keeps going past failures and interrupts, reporting the first error
+    fn run_cleanup(&self, task: &str, items: &[PlanItem]) -> Result<(), ExecutionError> {
+        let mut first_error = None;
+        for item in items {
+            let result = match item {
+                PlanItem::Step(step) => self.run_step(step),
+                PlanItem::Task(plan) => self
+                    .run_cleanup(&plan.name, &plan.items)
+                    .and(self.run_cleanup(&plan.name, &plan.finally)),
+            };
+            if let Err(e) = result {
+                eprintln!(
+                    "cleanup for task `{}` failed:\n    {}",
+                    task,
+                    self.plan.secrets.mask(&e.to_string())
+                );
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(()),
         }
-        base_cmd.arg(base_script);
-        return base_cmd;
     }
-    pub fn execute_tasks(&self) -> Result<(), ExecutionError> {
-        let command = self.parse_strings_into_single_command();
-        Self::call_command(command)?;
-        Ok(())
+    // on_fail commands can reference the failure through environment variables, which the
+    // shell expands without running anything that is in the failed command
+    fn with_failure_variables(items: &[PlanItem], failure: &StepFailure) -> Vec<PlanItem> {
+        let failure_vars = vec![
+            ("failed_task".to_string(), failure.task.to_owned()),
+            ("failed_command".to_string(), failure.command.to_owned()),
+            ("exit_code".to_string(), failure.exit_code.to_string()),
+        ];
+        let mut new_items = items.to_owned();
+        for item in new_items.iter_mut() {
+            match item {
+                PlanItem::Step(step) => step.env.extend(failure_vars.to_owned()),
+                PlanItem::Task(plan) => {
+                    plan.items = Self::with_failure_variables(&plan.items, failure)
+                }
+            }
+        }
+        new_items
+    }
+    fn run_plan(&self, plan: &TaskPlan) -> Result<(), ExecutionError> {
+        let result = match plan.mode {
+            RunMode::Steps => self.run_items(&plan.items),
+            RunMode::Script => match self.run_script(plan) {
+                // the script died of the signal tasker passed on, that is not a failure of its own
+                Err(e) if e.failure().is_some() && signals::interrupted() => {
+                    Err(Self::interrupted_error())
+                }
+                result => result,
+            },
+        };
+        if let Some(failure) = result.as_ref().err().and_then(|e| e.failure()) {
+            let on_fail = Self::with_failure_variables(&plan.on_fail, failure);
+            // a failing handler should not hide the original failure
+            if let Err(e) = self.run_items(&on_fail) {
+                eprintln!(
+                    "on_fail for task `{}` failed:\n    {}",
+                    plan.name,
+                    self.plan.secrets.mask(&e.to_string())
+                );
+            }
+        }
+        let mut cleanup = Ok(());
+        if plan.mode == RunMode::Script {
+            // sub tasks were folded into the script, so their cleanup is ours to run
+            for sub_task in plan.sub_tasks() {
+                cleanup = cleanup.and(self.run_cleanup(&sub_task.name, &sub_task.finally));
+            }
+        }
+        cleanup = cleanup.and(self.run_cleanup(&plan.name, &plan.finally));
+        result.and(cleanup)
     }
-    pub fn print_commands(&self) {
-        for i in 0..self.commands.len() {
-            println!("{:?}: {}", i, self.commands[i]);
+    // runs the plan without asking, `confirm` has to be called first for the questions
+    pub fn execute_tasks(&self) -> Result<(), ExecutionError> {
+        if self.handle_signals {
+            signals::install();
+        }
+        if self.raw_output && !self.plan.secrets.is_empty() {
+            eprintln!("\x1b[33m>\x1b[0m the task uses secrets, so its output is masked and --raw is ignored");
         }
+        // errors carry the commands that failed, which have the values filled in
+        self.run_plan(&self.plan)
+            .map_err(|e| e.masked(&self.plan.secrets))
     }
 }
 
 pub struct TaskBuilder {
     config: Taskfile,
     variable_lookup: HashMap<String, String>,
+    // every value each variable was given and where from, for `tasker explain`
+    variable_sources: BTreeMap<String, Vec<VariableSource>>,
+    context_names: Vec<String>,
+    // secrets that could not be read, with the reason, only an error once a command uses them
+    missing_secrets: Vec<(String, String)>,
+    // values of secrets, secret args and secret context keys, masked in whatever is printed
+    secrets: Secrets,
     clap_config: clap::Command,
 }
 
 impl TaskBuilder {
     pub fn new(config: Taskfile) -> Self {
-        let clp_config = config.create_clap_command();
+        let clp_config = config
+            .create_clap_command()
+            .subcommands(Builtin::clap_commands(&config));
         Self {
             config,
             variable_lookup: HashMap::new(),
+            variable_sources: BTreeMap::new(),
+            context_names: vec![],
+            missing_secrets: vec![],
+            secrets: Secrets::default(),
             clap_config: clp_config,
         }
     }
@@ -110,52 +420,133 @@ impl TaskBuilder {
 
     // used for getting defaults and subtask values
     fn update_variables_from_task_stanza(&mut self, task: TaskStanza) {
-        let mut local_variable_lookup = self.variable_lookup.clone();
         for cmd in task.get_command_args() {
             let value = match cmd.get_default() {
                 Some(value) => value,
                 None => continue,
             };
             let key = cmd.get_name();
-            upsert_into_hash_map(
-                key.to_string(),
-                value.to_string(),
-                &mut local_variable_lookup,
-            );
+            let location = self.config.locate_arg(&task.name, key);
+            let source = VariableSource::new(Layer::Default, value.to_string())
+                .with_origin(format!("of task `{}`", task.name))
+                .with_location(location);
+            self.set_variable(key.to_string(), source);
         }
-        self.variable_lookup = local_variable_lookup;
     }
     fn update_variables_from_arg_matches(&mut self, args: &ArgMatches) {
-        let mut local_variable_lookup = self.variable_lookup.clone();
         for id in args.ids() {
             let key = id.to_string();
+            let env_name = format!("TASKER_{}", key.to_uppercase());
             let mut value = args.get_one::<String>(id.as_str()).unwrap().to_string();
+            let mut layer = match args.value_source(id.as_str()) {
+                // clap hands back the task's default, which has always won over contexts
+                Some(ValueSource::DefaultValue) => {
+                    let sources = self.variable_sources.entry(key.to_owned()).or_default();
+                    let source = match sources.iter().position(|s| s.layer == Layer::Default) {
+                        Some(index) => sources.remove(index),
+                        None => VariableSource::new(Layer::Default, value),
+                    };
+                    self.set_variable(key, source);
+                    continue;
+                }
+                Some(ValueSource::EnvVariable) => Layer::Env,
+                _ => Layer::CommandLine,
+            };
             // I kinda hate this but until we get flag based arg, this allows us to skip ordered args
             if value == "-" {
-                let env_attempt = format!("TASKER_{}", key.to_uppercase());
-                value = std::env::var(env_attempt).unwrap();
+                value = std::env::var(&env_name).unwrap();
+                layer = Layer::Env;
             }
-            upsert_into_hash_map(key, value, &mut local_variable_lookup);
+            let source = match layer {
+                Layer::Env => {
+                    VariableSource::new(layer, value).with_origin(format!("`{}`", env_name))
+                }
+                _ => VariableSource::new(layer, value),
+            };
+            self.set_variable(key, source);
         }
-        self.variable_lookup = local_variable_lookup;
     }
-    fn update_variables_from_context(&mut self, context: HashMap<String, String>) {
-        let mut local_variable_lookup = self.variable_lookup.clone();
-        for (key, value) in context.iter() {
-            upsert_into_hash_map(
-                key.to_string(),
-                value.to_string(),
-                &mut local_variable_lookup,
-            );
+    fn update_variables_from_context(&mut self, context_name: &str) {
+        let context = self.config.get_context(&[context_name.to_string()]);
+        let mut keys: Vec<&String> = context.keys().collect();
+        keys.sort();
+        for key in keys {
+            let origin = self
+                .config
+                .context_value_origin(context_name, key)
+                .unwrap_or_else(|| context_name.to_string());
+            let location = self.config.locate_context_value(&origin, key);
+            let origin = match origin == context_name {
+                true => format!("`{}`", origin),
+                false => format!("`{}` through `{}`", origin, context_name),
+            };
+            let source = VariableSource::new(Layer::Context, context[key].to_owned())
+                .with_origin(origin)
+                .with_location(location);
+            self.set_variable(key.to_owned(), source);
         }
-        self.variable_lookup = local_variable_lookup;
+    }
+
+    fn update_variables_from_secrets(&mut self) {
+        let sources = self.config.secrets.clone();
+        for (name, source) in sources {
+            match source.read() {
+                Ok(value) => {
+                    self.secrets.register(&value);
+                    let location = self.config.locate_secret(&name);
+                    let source = VariableSource::new(Layer::Secret, value)
+                        .with_origin(format!("read from {}", source))
+                        .with_location(location);
+                    self.set_variable(name, source);
+                }
+                Err(e) => self.missing_secrets.push((name, e)),
+            }
+        }
+    }
+    // values of secret args and context keys, whatever source they ended up coming from
+    fn register_secret_variables(&mut self, selected_task: &TaskStanza) {
+        let mut keys = self.config.context_secret_keys(&self.context_names);
+        for arg in selected_task.get_command_args() {
+            if arg.secret {
+                keys.push(arg.get_name().to_string());
+            }
+        }
+        for key in keys {
+            if let Some(value) = self.variable_lookup.get(&key) {
+                self.secrets.register(value);
+            }
+        }
+    }
+    // a secret that could not be read is only a problem for the commands that use it
+    fn check_missing_secrets(&self, plan: &TaskPlan) -> Result<(), UserFacingError> {
+        let commands: Vec<&str> = plan
+            .all_steps()
+            .iter()
+            .map(|step| step.command.as_str())
+            .collect();
+        let messages: Vec<String> = self
+            .missing_secrets
+            .iter()
+            .filter(|(name, _)| {
+                let reference = format!("${{{}}}", name);
+                commands.iter().any(|command| command.contains(&reference))
+            })
+            .map(|(name, reason)| format!("secret `{}` could not be read: {}", name, reason))
+            .collect();
+        if messages.is_empty() {
+            return Ok(());
+        }
+        return Err(UserFacingError::MissingArgError(ErrWithMessage {
+            code: "SECRET_NOT_FOUND".to_string(),
+            messages,
+        }));
     }
 
     fn replace_string_with_args(string: String, local_vars: &HashMap<String, String>) -> String {
         let mut new_string = string;
         for (key, value) in local_vars.iter() {
             if value.contains(" ") {
-                new_string = new_string.replace(&format!("${{{}}}", key), &format!("{}", value));
+                new_string = new_string.replace(&format!("${{{}}}", key), &value.to_string());
             } else {
                 new_string = new_string.replace(&format!("${{{}}}", key), value);
             }
@@ -166,22 +557,26 @@ impl TaskBuilder {
         &mut self,
         selected_task: &TaskStanza,
         task_name: String,
-        selected_context: HashMap<String, String>,
         cli_inputs: ArgMatches,
     ) {
-        // 3. defaults
+        // 4. defaults
         self.update_variables_from_task_stanza(selected_task.to_owned());
-        // 2. context
-        self.update_variables_from_context(selected_context);
+        // 3. secrets
+        self.update_variables_from_secrets();
+        // 2. contexts, in the order they were given
+        let context_names = self.context_names.clone();
+        for context_name in context_names {
+            self.update_variables_from_context(&context_name);
+        }
         // 1. cli input
-        self.update_variables_from_arg_matches(&cli_inputs.subcommand_matches(&task_name).unwrap());
+        self.update_variables_from_arg_matches(cli_inputs.subcommand_matches(&task_name).unwrap());
     }
-    fn get_context_from_matches(&self, matches: &ArgMatches) -> Option<String> {
-        let context_name = match matches.get_one::<String>("context") {
-            Some(context_name) => Some(context_name.to_string()),
-            None => return None,
+    fn get_context_from_matches(&self, matches: &ArgMatches) -> Vec<String> {
+        let context_names = match matches.get_many::<String>("context") {
+            Some(context_names) => context_names.map(|name| name.to_string()).collect(),
+            None => vec![],
         };
-        return context_name;
+        return context_names;
     }
     fn get_task_name_from_matches(&self, sub_matches: &ArgMatches) -> String {
         // get matches found so far and parse into subcommand
@@ -191,15 +586,20 @@ impl TaskBuilder {
     }
     fn parse_cli_inputs(
         &mut self,
+        raw_args: &[String],
         initial_arg_matches: &ArgMatches,
     ) -> Result<ArgMatches, UserFacingError> {
-        // we can be confident in unwraps since we verify most values above on load
-        let raw_args: Vec<_> = initial_arg_matches
-            .get_many::<String>("task_info")
-            .unwrap()
-            .collect();
-
-        let cli_inputs = match self.clap_config.to_owned().try_get_matches_from(raw_args) {
+        let mut parsed = self.clap_config.to_owned().try_get_matches_from(raw_args);
+        if let Err(e) = &parsed {
+            let interactive = initial_arg_matches.get_flag("interactive");
+            let missing = e.kind() == clap::error::ErrorKind::MissingRequiredArgument;
+            if missing && prompt::is_interactive() {
+                if let Some(args) = self.prompt_for_missing_args(raw_args, interactive) {
+                    parsed = self.clap_config.to_owned().try_get_matches_from(args);
+                }
+            }
+        }
+        let cli_inputs = match parsed {
             Ok(cli_inputs) => cli_inputs,
             Err(e) => {
                 return Err(UserFacingError::MissingArgError(ErrWithMessage {
@@ -212,112 +612,401 @@ impl TaskBuilder {
         return Ok(cli_inputs);
     }
 
-    pub fn gather_task_info_from_cli(
-        &mut self,
-        task_name: &String,
-        context_name: Option<String>,
-    ) -> (TaskStanza, HashMap<String, String>) {
-        // TODO: make this a function
+    fn unknown_context_error(&self, name: &str) -> UserFacingError {
+        let names = self.config.context_names();
+        let mut messages = vec![format!("available contexts: {}", names.join(", "))];
+        if names.is_empty() {
+            messages = vec!["the Taskfile does not define any contexts".to_string()];
+        }
+        let mut message = format!("context `{}` does not exist", name);
+        if let Some(suggestion) = closest_match(name, &names) {
+            message.push_str(&format!(", did you mean `{}`?", suggestion));
+        }
+        messages.push(message);
+        return UserFacingError::InvalidContext(ErrWithMessage {
+            code: "UNKNOWN_CONTEXT".to_string(),
+            messages,
+        });
+    }
+    // falls back to the default context and rejects contexts a task was not written for
+    fn select_contexts(
+        &self,
+        task_name: &str,
+        context_names: Vec<String>,
+    ) -> Result<Vec<String>, UserFacingError> {
+        let context_names = match context_names.is_empty() {
+            true => self.config.default_context.iter().cloned().collect(),
+            false => context_names,
+        };
+        if let Some(name) = context_names
+            .iter()
+            .find(|name| !self.config.contexts.contains_key(*name))
+        {
+            return Err(self.unknown_context_error(name));
+        }
+        for task in self.config.reachable_tasks(task_name) {
+            if task.contexts.is_empty() {
+                continue;
+            }
+            let allowed = !context_names.is_empty()
+                && context_names
+                    .iter()
+                    .all(|name| task.contexts.contains(name));
+            if !allowed {
+                return Err(UserFacingError::InvalidContext(ErrWithMessage {
+                    code: "CONTEXT_NOT_ALLOWED".to_string(),
+                    messages: vec![format!(
+                        "task `{}` only runs under the contexts {}, got {}",
+                        task.name,
+                        task.contexts.join(", "),
+                        match context_names.is_empty() {
+                            true => "none".to_string(),
+                            false => context_names.join(", "),
+                        }
+                    )],
+                }));
+            }
+        }
+        return Ok(context_names);
+    }
+
+    pub(crate) fn gather_task_info_from_cli(&mut self, task_name: &str) -> TaskStanza {
         let cfg = self.get_config().to_owned();
         let selected_task = cfg.get_task_by_name(task_name).unwrap();
-        let task_context = self.config.get_context(context_name);
-        return (selected_task.to_owned(), task_context.to_owned());
+        return selected_task.to_owned();
     }
 
-    pub fn create_command_strings(
+    pub(crate) fn create_task_plan(
         &mut self,
         initial_arg_matches: ArgMatches,
-    ) -> Result<Vec<String>, UserFacingError> {
-        let cli_inputs = self.parse_cli_inputs(&initial_arg_matches)?;
-        let context_name = self.get_context_from_matches(&initial_arg_matches);
+    ) -> Result<TaskPlan, UserFacingError> {
+        // we can be confident in unwraps since we verify most values above on load
+        let raw_args: Vec<String> = initial_arg_matches
+            .get_many::<String>("task_info")
+            .unwrap()
+            .cloned()
+            .collect();
+        return self.plan_from_args(&raw_args, &initial_arg_matches);
+    }
+
+    // plans the task named by the first of `raw_args`, with the rest as its args and the
+    // global options from `initial_arg_matches`
+    pub(crate) fn plan_from_args(
+        &mut self,
+        raw_args: &[String],
+        initial_arg_matches: &ArgMatches,
+    ) -> Result<TaskPlan, UserFacingError> {
+        let cli_inputs = self.parse_cli_inputs(raw_args, initial_arg_matches)?;
+        let context_names = self.get_context_from_matches(initial_arg_matches);
+        return self.plan_from_matches(cli_inputs, context_names);
+    }
+
+    // plans `task_name` with `args` in the order the command line takes them, under the
+    // contexts `-x` would pick, without prompting for anything missing
+    pub fn plan(
+        &mut self,
+        task_name: &str,
+        args: &[String],
+        context_names: &[String],
+    ) -> Result<TaskPlan, UserFacingError> {
+        if self.config.get_task_by_name(task_name).is_none() {
+            return Err(UserFacingError::TaskDoesNotExist(ErrWithMessage {
+                code: "TASK_ERROR".to_string(),
+                messages: vec![format!("task `{}` does not exist", task_name)],
+            }));
+        }
+        let mut raw_args = vec![task_name.to_string()];
+        raw_args.extend(args.iter().cloned());
+        let cli_inputs = match self.clap_config.to_owned().try_get_matches_from(raw_args) {
+            Ok(cli_inputs) => cli_inputs,
+            Err(e) => {
+                return Err(UserFacingError::MissingArgError(ErrWithMessage {
+                    code: "MISSING_ARGUMENT".to_string(),
+                    messages: vec!["\n".to_owned() + &e.render().to_string()],
+                }))
+            }
+        };
+        return self.plan_from_matches(cli_inputs, context_names.to_vec());
+    }
+
+    fn plan_from_matches(
+        &mut self,
+        cli_inputs: ArgMatches,
+        context_names: Vec<String>,
+    ) -> Result<TaskPlan, UserFacingError> {
         let task_name = self.get_task_name_from_matches(&cli_inputs);
-        let (selected_task, selected_context) =
-            self.gather_task_info_from_cli(&task_name, context_name);
+        self.context_names = self.select_contexts(&task_name, context_names)?;
+        let selected_task = self.gather_task_info_from_cli(&task_name);
 
-        self.load_variables(&selected_task, task_name, selected_context, cli_inputs);
+        self.load_variables(&selected_task, task_name, cli_inputs);
+        self.register_secret_variables(&selected_task);
         let cloned_vars = self.variable_lookup.clone();
-        return Ok(self.get_all_commands_parsed(selected_task, cloned_vars));
+        let mut plan = self.get_all_commands_parsed(selected_task, cloned_vars)?;
+        plan.secrets.extend(&self.secrets);
+        self.check_missing_secrets(&plan)?;
+        return Ok(plan);
+    }
+
+    // expands a task without command line input, args fall back to their defaults and
+    // required ones stay as `${name}`, and so do calls of a task named by one
+    pub fn plan_with_defaults(&self, task: &TaskStanza) -> Result<TaskPlan, UserFacingError> {
+        let mut variables = HashMap::new();
+        for arg in task.get_command_args() {
+            if let Some(default) = arg.get_default() {
+                variables.insert(arg.get_name().to_string(), default.to_string());
+            }
+        }
+        return self.expand_task(task.to_owned(), variables, true);
     }
 
     fn get_all_commands_parsed(
         &self,
         task: TaskStanza,
         current_variables: HashMap<String, String>,
-    ) -> Vec<String> {
-        // return a list of filled in commands for a given task
+    ) -> Result<TaskPlan, UserFacingError> {
+        return self.expand_task(task, current_variables, false);
+    }
 
-        let mut commands: Vec<String> = Vec::new();
+    // `keep_unresolved` turns `task:` calls whose task name comes from a variable that is
+    // not set yet into placeholders, for looking at a task without running it
+    fn expand_task(
+        &self,
+        task: TaskStanza,
+        current_variables: HashMap<String, String>,
+        keep_unresolved: bool,
+    ) -> Result<TaskPlan, UserFacingError> {
+        // return a plan of filled in commands for a given task
         let mut local_vars = current_variables.to_owned();
-        for cmd in task.commands {
+        let items = self.parse_commands(&task, &task.commands, &mut local_vars, keep_unresolved)?;
+        // handlers see the same variables as the commands of their task
+        let on_fail = self.parse_commands(
+            &task,
+            &task.on_fail,
+            &mut local_vars.to_owned(),
+            keep_unresolved,
+        )?;
+        let finally = self.parse_commands(
+            &task,
+            &task.finally,
+            &mut local_vars.to_owned(),
+            keep_unresolved,
+        )?;
+        let mut plan = TaskPlan {
+            name: task.name.to_owned(),
+            args: vec![],
+            mode: task.mode.unwrap_or(self.config.mode),
+            ignore_error: false,
+            retries: task.retries.to_owned(),
+            timeout: task.timeout.map(|timeout| timeout.0),
+            interactive: task.interactive,
+            shell: self.shell_for(&task),
+            strict: self.strict_for(&task),
+            confirm: task
+                .confirm
+                .to_owned()
+                .map(|question| Self::replace_string_with_args(question, &local_vars)),
+            items,
+            on_fail,
+            finally,
+            unresolved: false,
+            secrets: Secrets::default(),
+        };
+        // secret args passed to sub tasks are masked wherever the task runs
+        let sub_plans: Vec<&TaskPlan> = [&plan.items, &plan.on_fail, &plan.finally]
+            .into_iter()
+            .flatten()
+            .filter_map(|item| match item {
+                PlanItem::Task(sub_plan) => Some(sub_plan),
+                PlanItem::Step(_) => None,
+            })
+            .collect();
+        let mut secrets = Secrets::default();
+        for sub_plan in sub_plans {
+            secrets.extend(&sub_plan.secrets);
+        }
+        plan.secrets = secrets;
+        // commands that can not share the script run in steps mode, each in its own process
+        if plan.mode == RunMode::Script && !plan.fits_one_script() {
+            plan.mode = RunMode::Steps;
+        }
+        return Ok(plan);
+    }
+
+    fn shell_for(&self, task: &TaskStanza) -> Shell {
+        match &task.shell {
+            Some(shell) => shell.to_owned(),
+            None => self.config.shell.to_owned(),
+        }
+    }
+
+    fn strict_for(&self, task: &TaskStanza) -> bool {
+        task.strict.unwrap_or(self.config.strict)
+    }
+
+    fn parse_commands(
+        &self,
+        task: &TaskStanza,
+        task_commands: &[TaskCmd],
+        local_vars: &mut HashMap<String, String>,
+        keep_unresolved: bool,
+    ) -> Result<Vec<PlanItem>, UserFacingError> {
+        let mut items: Vec<PlanItem> = Vec::new();
+        for cmd in task_commands {
             let command_type = cmd.key.to_owned();
             let raw_command = cmd.value.to_owned();
             match command_type {
                 // base case
                 CommandTypes::Shell(_) => {
-                    let parsed_command = Self::replace_string_with_args(raw_command, &local_vars);
-                    commands.push(parsed_command)
+                    let parsed_command =
+                        Self::replace_string_with_args(raw_command.to_owned(), local_vars);
+                    items.push(PlanItem::Step(Step {
+                        task: task.name.to_owned(),
+                        command: parsed_command,
+                        template: raw_command,
+                        called_from: vec![],
+                        ignore_error: cmd.ignore_error,
+                        retries: cmd.retries.to_owned().or(task.retries.to_owned()),
+                        timeout: cmd.timeout.or(task.timeout).map(|timeout| timeout.0),
+                        interactive: cmd.interactive || task.interactive,
+                        shell: cmd
+                            .interpreter
+                            .to_owned()
+                            .unwrap_or_else(|| self.shell_for(task)),
+                        strict: self.strict_for(task),
+                        env: vec![],
+                    }))
                 }
                 CommandTypes::Task(_) => {
                     // fill in variables, then recurse through the subtask
-                    let parsed_command = Self::replace_string_with_args(raw_command, &local_vars);
+                    let parsed_command =
+                        Self::replace_string_with_args(raw_command.to_owned(), local_vars);
                     let sub_task_name: String = Self::parse_task_name_from_string(&parsed_command);
                     let sub_task_supplied_args: Vec<String> =
                         Self::parse_task_args_from_string(&parsed_command);
-                    let sub_task = self.config.get_task_by_name(&sub_task_name).unwrap();
+                    let sub_task = match self.config.get_task_by_name(&sub_task_name) {
+                        Some(sub_task) => sub_task,
+                        None if keep_unresolved
+                            && Self::parse_task_name_from_string(&raw_command).contains("${") =>
+                        {
+                            items.push(PlanItem::Task(TaskPlan {
+                                name: sub_task_name,
+                                args: sub_task_supplied_args,
+                                ignore_error: cmd.ignore_error,
+                                unresolved: true,
+                                ..Default::default()
+                            }));
+                            continue;
+                        }
+                        None => {
+                            let message = match sub_task_name.contains("${") {
+                                true => format!(
+                                    "task `{}` calls `{}`, which needs a variable that is not set",
+                                    task.name, sub_task_name
+                                ),
+                                false => format!(
+                                    "task `{}` calls `{}`, which does not exist",
+                                    task.name, sub_task_name
+                                ),
+                            };
+                            return Err(UserFacingError::TaskfileParseError(ErrWithMessage {
+                                code: "TASK_ERROR".to_string(),
+                                messages: vec![message],
+                            }));
+                        }
+                    };
                     let sub_task_expected_args = sub_task.get_command_args();
-                    for i in 0..sub_task_expected_args.len() {
-                        let arg = &sub_task_expected_args[i];
+                    let mut secrets = Secrets::default();
+                    for (i, arg) in sub_task_expected_args.iter().enumerate() {
                         let key = arg.get_name();
-                        let value = match sub_task_supplied_args.get(i) {
-                            Some(_) => sub_task_supplied_args[i].to_string(),
-                            None => {
-                                // TODO: handle error here for missing argument
-                                sub_task_expected_args[i].get_default().unwrap().to_string()
+                        let value = match (sub_task_supplied_args.get(i), arg.get_default()) {
+                            (Some(supplied), _) => supplied.to_string(),
+                            (None, Some(default)) => default.to_string(),
+                            (None, None) => {
+                                return Err(UserFacingError::MissingArgError(ErrWithMessage {
+                                    code: "MISSING_ARGUMENT".to_string(),
+                                    messages: vec![format!(
+                                        "task `{}` calls `{}` without `{}`, which has no default",
+                                        task.name, sub_task_name, key
+                                    )],
+                                }))
                             }
                         };
+                        if arg.secret {
+                            secrets.register(&value);
+                        }
                         upsert_into_hash_map(
                             key.to_string().to_owned(),
                             value.to_owned(),
-                            &mut local_vars,
+                            local_vars,
                         );
                     }
-                    commands.extend(
-                        self.get_all_commands_parsed(sub_task.to_owned(), local_vars.to_owned()),
-                    );
+                    // options on a `task:` command become the defaults of the sub task
+                    let mut sub_task = sub_task.to_owned();
+                    if cmd.retries.is_some() {
+                        sub_task.retries = cmd.retries.to_owned();
+                    }
+                    if cmd.timeout.is_some() {
+                        sub_task.timeout = cmd.timeout;
+                    }
+                    sub_task.interactive = sub_task.interactive || cmd.interactive;
+                    if cmd.interpreter.is_some() {
+                        sub_task.shell = cmd.interpreter.to_owned();
+                    }
+                    let mut sub_plan =
+                        self.expand_task(sub_task, local_vars.to_owned(), keep_unresolved)?;
+                    sub_plan.secrets.extend(&secrets);
+                    sub_plan.ignore_error = cmd.ignore_error;
+                    sub_plan.args = sub_task_supplied_args;
+                    sub_plan.add_caller(&parsed_command);
+                    items.push(PlanItem::Task(sub_plan));
                 }
                 _ => unimplemented!(),
             }
         }
-        return commands;
+        return Ok(items);
     }
-    pub fn create_task_runner(
+    pub(crate) fn create_task_runner(
         &mut self,
         initial_arg_matches: ArgMatches,
     ) -> Result<TaskRunner, UserFacingError> {
-        let commands = self.create_command_strings(initial_arg_matches)?;
-        let task_runner = TaskRunner::new(commands);
-        return Ok(task_runner);
+        let raw_output = initial_arg_matches.get_flag("raw");
+        let plan = self.create_task_plan(initial_arg_matches)?;
+        return Ok(self.runner(plan, raw_output));
     }
-    pub fn parse_task_name_from_string(parsed_command: &String) -> String {
+    // a runner for a plan of this builder, asking for the protected contexts it was planned under
+    pub fn runner(&self, plan: TaskPlan, raw_output: bool) -> TaskRunner {
+        let protected_contexts = self
+            .context_names
+            .iter()
+            .filter(|name| self.config.is_context_protected(name))
+            .cloned()
+            .collect();
+        return TaskRunner::new(plan, raw_output).with_protected_contexts(protected_contexts);
+    }
+    pub fn parse_task_name_from_string(parsed_command: &str) -> String {
         return split_exclude_quotes(parsed_command.to_string())[0].to_string();
     }
-    pub fn parse_task_args_from_string(parsed_command: &String) -> Vec<String> {
+    pub fn parse_task_args_from_string(parsed_command: &str) -> Vec<String> {
         return split_exclude_quotes(parsed_command.to_string())[1..].to_vec();
     }
 }
 #[cfg(test)]
 mod tests {
-    use super::{TaskBuilder, TaskRunner};
-    use crate::utils::test_helpers::test_helpers::load_from_string;
+    use super::{
+        errors::{ExecutionError, StepFailure},
+        Finished, Layer, OutputMode, PlanItem, Step, TaskBuilder, TaskPlan, TaskRunner,
+    };
+    use crate::taskfile::{RetryPolicy, RunMode, Shell, Taskfile};
+    use crate::utils::{errors::UserFacingError, test_helpers::test_helpers::load_from_string};
     use clap::{value_parser, Arg, Command};
-    use std::collections::HashMap;
+    use std::{collections::HashMap, time::Duration};
 
     #[test]
     fn test_parse_strings_into_single_command() {
-        let task = TaskRunner {
-            commands: vec!["echo hello".to_string(), "echo world".to_string()],
-        };
-        let cmd = task.parse_strings_into_single_command();
+        let cmd = TaskRunner::parse_strings_into_single_command(
+            &Shell::default(),
+            vec!["echo hello".to_string(), "echo world".to_string()],
+        );
         let arg = format!("{:?}", cmd).replace("\"", "");
         assert_eq!(arg, "sh -c echo hello;\\necho world;\\n");
     }
@@ -335,12 +1024,22 @@ mod tests {
     }
 
     #[test]
-    fn test_update_variables_from_hashmap() {
+    fn test_update_variables_from_context() {
         let mut runner = TaskBuilder::new(load_from_string());
-        let mut context = HashMap::new();
-        context.insert("test".to_string(), "test".to_string());
-        runner.update_variables_from_context(context);
-        assert_eq!(runner.variable_lookup.get("test").unwrap(), "test");
+        runner.update_variables_from_context("test");
+        assert_eq!(
+            runner.variable_lookup.get("test_key").unwrap(),
+            "test_value"
+        );
+        runner.update_variables_from_context("test-child");
+        assert_eq!(
+            runner.variable_lookup.get("test_key").unwrap(),
+            "child_value"
+        );
+        let sources = &runner.variable_sources()["test_key"];
+        assert_eq!(sources.len(), 2);
+        assert_eq!(sources[0].to_string(), "context `test`");
+        assert_eq!(sources[1].to_string(), "context `test-child`");
     }
     #[test]
     fn test_update_variables_from_task_stanza() {
@@ -354,36 +1053,595 @@ mod tests {
     }
     #[test]
     fn test_replace_string_with_args() {
-        let mut runner = TaskBuilder::new(load_from_string());
         let map: HashMap<String, String> =
             HashMap::from([("test".to_string(), "test".to_string())]);
-        runner.update_variables_from_context(map);
-        let new_string = TaskBuilder::replace_string_with_args(
-            "test ${test}".to_string(),
-            &runner.variable_lookup,
-        );
+        let new_string = TaskBuilder::replace_string_with_args("test ${test}".to_string(), &map);
         assert_eq!(new_string, "test test");
     }
     #[test]
+    fn test_select_contexts() {
+        let mut builder = TaskBuilder::new(load_from_string());
+        let selected = builder.select_contexts("test-cmd", vec!["test".to_string()]);
+        assert_eq!(selected.unwrap(), vec!["test"]);
+        let error = builder
+            .select_contexts("test-cmd", vec!["tset".to_string()])
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("context `tset` does not exist, did you mean `test`?"));
+        assert!(error
+            .to_string()
+            .contains("available contexts: test, test-child"));
+
+        builder.config.default_context = Some("test-child".to_string());
+        assert_eq!(
+            builder.select_contexts("test-cmd", vec![]).unwrap(),
+            vec!["test-child"]
+        );
+        // restrictions apply to sub tasks too
+        builder.config.tasks[0].contexts = vec!["test".to_string()];
+        let error = builder.select_contexts("test-task", vec![]).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("task `test-cmd` only runs under the contexts test, got test-child"));
+        assert!(builder
+            .select_contexts("test-task", vec!["test".to_string()])
+            .is_ok());
+    }
+    #[test]
+    fn test_missing_secrets_only_fail_when_used() {
+        let mut config = load_from_string();
+        config.secrets = serde_yaml::from_str(
+            "unused: {env: TASKER_TEST_UNSET_SECRET}\nrequired_arg: {env: TASKER_TEST_UNSET_SECRET}",
+        )
+        .unwrap();
+        let mut builder = TaskBuilder::new(config);
+        builder.update_variables_from_secrets();
+        let task = builder.get_config().get_task_by_name("test-cmd").unwrap();
+        let plan = builder
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap();
+        let error = builder
+            .check_missing_secrets(&plan)
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("secret `required_arg` could not be read"));
+        assert!(!error.contains("unused"));
+    }
+    #[test]
     fn test_parse_task_name_from_string() {
-        let task_name = TaskBuilder::parse_task_name_from_string(&"test".to_string());
+        let task_name = TaskBuilder::parse_task_name_from_string("test");
         assert_eq!(task_name, "test");
     }
     #[test]
     fn test_get_all_commands_parsed_with_task() {
         let runner = TaskBuilder::new(load_from_string());
         let task = runner.get_config().get_task_by_name("test-task").unwrap();
-        let commands = runner.get_all_commands_parsed(task.to_owned(), HashMap::new());
-        assert_eq!(commands[0], "echo Hello Foo Bar");
-        assert_eq!(commands[1], "echo Hello Bar Foo");
+        let plan = runner
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap();
+        let commands = plan.steps();
+        assert_eq!(commands[0].command, "echo Hello Foo Bar");
+        assert_eq!(commands[1].command, "echo Hello Bar Foo");
+    }
+    #[test]
+    fn test_get_all_commands_parsed_with_shells() {
+        let mut config = load_from_string();
+        config.shell = serde_yaml::from_str("bash -c").unwrap();
+        let runner = TaskBuilder::new(config);
+        let task = runner.get_config().get_task_by_name("test-shells").unwrap();
+        let plan = runner
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap();
+        let steps = plan.steps();
+        assert_eq!(plan.shell.to_string(), "bash -c");
+        assert_eq!(steps[0].shell.to_string(), "bash -c");
+        assert_eq!(steps[1].shell.to_string(), "python3 -c");
+        // the sub task brings its own shell
+        assert_eq!(steps[2].shell.to_string(), "sh -c");
     }
 
     #[test]
     fn test_error_on_missing_arg() {
         let runner = TaskBuilder::new(load_from_string());
         let task = runner.get_config().get_task_by_name("test-task").unwrap();
-        let commands = runner.get_all_commands_parsed(task.to_owned(), HashMap::new());
-        assert_eq!(commands[0], "echo Hello Foo Bar");
-        assert_eq!(commands[1], "echo Hello Bar Foo");
+        let plan = runner
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap();
+        let commands = plan.steps();
+        assert_eq!(commands[0].command, "echo Hello Foo Bar");
+        assert_eq!(commands[1].command, "echo Hello Bar Foo");
+    }
+
+    #[test]
+    fn test_error_on_unknown_sub_task() {
+        let yaml = r#"
+        tasks:
+          - name: dispatch
+            commands:
+              - task: ${target}
+            args:
+              - {name: target, type: string}
+          - name: broken
+            commands:
+              - task: nope
+            args: []
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let builder = TaskBuilder::new(taskfile);
+        let task = builder.get_config().get_task_by_name("dispatch").unwrap();
+        let error = builder
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("task `dispatch` calls `${target}`, which needs a variable that is not set"));
+        let task = builder.get_config().get_task_by_name("broken").unwrap();
+        let error = builder
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("task `broken` calls `nope`, which does not exist"));
+        // without input the call is kept as it is
+        let task = builder.get_config().get_task_by_name("dispatch").unwrap();
+        match &builder.plan_with_defaults(task).unwrap().items[0] {
+            PlanItem::Task(plan) => {
+                assert!(plan.unresolved);
+                assert_eq!(plan.name, "${target}");
+            }
+            _ => panic!("expected a placeholder for the call"),
+        }
+    }
+
+    #[test]
+    fn test_plan() {
+        let mut builder = TaskBuilder::new(load_from_string());
+        let plan = builder
+            .plan("test-cmd", &["Peter".to_string()], &["test".to_string()])
+            .unwrap();
+        assert_eq!(plan.steps()[0].command, "echo Hello Peter default");
+        assert_eq!(builder.context_names(), ["test"]);
+        assert!(matches!(
+            builder.plan("test-cmd", &[], &[]),
+            Err(UserFacingError::MissingArgError(_))
+        ));
+        assert!(matches!(
+            builder.plan("nope", &[], &[]),
+            Err(UserFacingError::TaskDoesNotExist(_))
+        ));
+        assert!(builder
+            .plan("test-cmd", &["Peter".to_string()], &["nope".to_string()])
+            .is_err());
+    }
+    #[test]
+    fn test_default_wins_over_context() {
+        let yaml = r#"
+        contexts:
+          prod:
+            region: eu-west
+            zone: a
+        tasks:
+          - name: deploy
+            commands:
+              - shell: echo ${region} ${zone}
+            args:
+              - {name: region, type: string, default: local}
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let mut builder = TaskBuilder::new(taskfile);
+        let plan = builder.plan("deploy", &[], &["prod".to_string()]).unwrap();
+        // an arg's default beats contexts, which only fill in what the task does not define
+        assert_eq!(plan.steps()[0].command, "echo local a");
+        let layers: Vec<Layer> = builder.variable_sources()["region"]
+            .iter()
+            .map(|source| source.layer)
+            .collect();
+        assert_eq!(layers, vec![Layer::Context, Layer::Default]);
+    }
+    #[test]
+    fn test_plan_sub_task_errors() {
+        let yaml = r#"
+        tasks:
+          - name: greet
+            commands:
+              - shell: echo hi ${who}
+            args:
+              - {name: who, type: string}
+          - name: greet-all
+            commands:
+              - task: greet
+            args: []
+          - name: dispatch
+            commands:
+              - task: ${target}
+            args:
+              - {name: target, type: string}
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let mut builder = TaskBuilder::new(taskfile);
+        match builder.plan("greet-all", &[], &[]) {
+            Err(UserFacingError::MissingArgError(e)) => assert!(e
+                .to_string()
+                .contains("task `greet-all` calls `greet` without `who`, which has no default")),
+            _ => panic!("expected the call without `who` to fail"),
+        }
+        assert!(matches!(
+            builder.plan("dispatch", &["nope".to_string()], &[]),
+            Err(UserFacingError::TaskfileParseError(_))
+        ));
+    }
+
+    #[test]
+    fn test_interpreter_in_script_mode() {
+        let yaml = r#"
+        tasks:
+          - name: report
+            commands:
+              - shell: echo collecting
+              - shell: "[[ -n ${who} ]] && echo ${who}"
+                interpreter: bash -c
+            args:
+              - {name: who, type: string, default: me}
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let mut builder = TaskBuilder::new(taskfile);
+        let plan = builder.plan("report", &[], &[]).unwrap();
+        // sh does not know `[[`, so this only passes when bash runs the command
+        assert_eq!(plan.mode, RunMode::Steps);
+        let runner = builder.runner(plan, true);
+        assert!(runner
+            .render_tree()
+            .contains("shell: bash -c, env: inherited"));
+        assert!(runner.execute_tasks().is_ok());
+    }
+
+    #[test]
+    fn test_command_settings_in_script_mode() {
+        let yaml = r#"
+        tasks:
+          - name: slow
+            commands:
+              - shell: echo before
+              - shell: sleep 5
+                timeout: 200ms
+            args: []
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let mut builder = TaskBuilder::new(taskfile);
+        let plan = builder.plan("slow", &[], &[]).unwrap();
+        assert_eq!(plan.mode, RunMode::Steps);
+        match builder.runner(plan, true).execute_tasks() {
+            Err(ExecutionError::TimedOut(timeout)) => {
+                assert_eq!(timeout.failure.command, "sleep 5");
+                assert_eq!(timeout.failure.exit_code, 124);
+            }
+            _ => panic!("expected the timeout of the command to apply"),
+        }
+    }
+    #[test]
+    fn test_interactive_command_in_script_mode() {
+        let yaml = r#"
+        tasks:
+          - name: shell
+            commands:
+              - shell: echo before
+              - shell: test -z "$TASKER_STEP_MARKER"
+                interactive: true
+            args: []
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let mut builder = TaskBuilder::new(taskfile);
+        let plan = builder.plan("shell", &[], &[]).unwrap();
+        assert_eq!(plan.mode, RunMode::Steps);
+        assert!(plan.steps()[1].interactive && !plan.steps()[0].interactive);
+        // a command of its own, not a line of the task's script
+        assert!(builder.runner(plan, true).execute_tasks().is_ok());
+    }
+    #[test]
+    fn test_secrets_stay_with_their_plan() {
+        let yaml = r#"
+        tasks:
+          - name: login
+            commands:
+              - shell: test ${token} = nope
+            args:
+              - {name: token, type: string, secret: true}
+          - name: deploy
+            commands:
+              - task: login hunter2
+            args: []
+        "#;
+        let taskfile: Taskfile = serde_yaml::from_str(yaml).unwrap();
+        let mut builder = TaskBuilder::new(taskfile.to_owned());
+        let plan = builder.plan("deploy", &[], &[]).unwrap();
+        assert_eq!(plan.secrets.mask("hunter2"), "***");
+        match builder.runner(plan, false).execute_tasks() {
+            Err(ExecutionError::StepFailed(failure)) => {
+                assert_eq!(failure.command, "test *** = nope");
+                assert_eq!(failure.called_from, vec!["login ***"]);
+            }
+            _ => panic!("expected the comparison to fail"),
+        }
+        let mut other = TaskBuilder::new(taskfile);
+        let plan = other.plan("login", &["other".to_string()], &[]).unwrap();
+        assert_eq!(plan.secrets.mask("other hunter2"), "*** hunter2");
+    }
+    #[test]
+    fn test_check_finished_keeps_exit_code() {
+        let status = std::process::Command::new("sh")
+            .args(["-c", "exit 3"])
+            .status()
+            .unwrap();
+        let result = TaskRunner::check_finished(
+            StepFailure::new(&Step::default()),
+            Finished::Exited(status),
+        );
+        match result {
+            Err(ExecutionError::StepFailed(failure)) => assert_eq!(failure.exit_code, 3),
+            _ => panic!("expected the step to fail with its own exit code"),
+        }
+    }
+
+    fn step(command: &str, ignore_error: bool) -> PlanItem {
+        PlanItem::Step(Step {
+            command: command.to_string(),
+            ignore_error,
+            ..Default::default()
+        })
+    }
+    fn scratch_dir(name: &str) -> std::path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tasker-{}-{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+    #[test]
+    fn test_handlers_run_after_failure() {
+        let dir = scratch_dir("handlers");
+        let dir = dir.display();
+        let plan = TaskPlan {
+            name: "failing".to_string(),
+            items: vec![
+                PlanItem::Step(Step {
+                    task: "failing".to_string(),
+                    command: "exit 3".to_string(),
+                    ..Default::default()
+                }),
+                step("touch never", false),
+            ],
+            on_fail: vec![step(
+                &format!("echo ${{failed_task}} ${{exit_code}} > {}/on_fail", dir),
+                false,
+            )],
+            finally: vec![step(&format!("touch {}/finally", dir), false)],
+            ..Default::default()
+        };
+        let result = TaskRunner::new(plan, false).execute_tasks();
+        match result {
+            Err(ExecutionError::StepFailed(failure)) => assert_eq!(failure.exit_code, 3),
+            _ => panic!("expected the step failure to be returned"),
+        }
+        let on_fail = std::fs::read_to_string(format!("{}/on_fail", dir)).unwrap();
+        assert_eq!(on_fail, "failing 3\n");
+        assert!(std::path::Path::new(&format!("{}/finally", dir)).exists());
+    }
+    #[test]
+    fn test_failure_variables_are_not_run() {
+        let dir = scratch_dir("failure-variables");
+        let dir = dir.display();
+        let failing = format!("exit 3 # it's $(touch {}/injected)", dir);
+        let plan = TaskPlan {
+            name: "failing".to_string(),
+            items: vec![step(&failing, false)],
+            on_fail: vec![step(
+                &format!("echo \"${{failed_command}}\" > {}/on_fail", dir),
+                false,
+            )],
+            ..Default::default()
+        };
+        assert!(TaskRunner::new(plan, false).execute_tasks().is_err());
+        let on_fail = std::fs::read_to_string(format!("{}/on_fail", dir)).unwrap();
+        assert_eq!(on_fail, format!("{}\n", failing));
+        assert!(!std::path::Path::new(&format!("{}/injected", dir)).exists());
+    }
+    #[test]
+    fn test_ignore_error_continues() {
+        let dir = scratch_dir("ignore");
+        let dir = dir.display();
+        let plan = TaskPlan {
+            name: "ignoring".to_string(),
+            mode: RunMode::Steps,
+            items: vec![
+                step("exit 1", true),
+                step(&format!("touch {}/after", dir), false),
+            ],
+            on_fail: vec![step(&format!("touch {}/on_fail", dir), false)],
+            ..Default::default()
+        };
+        assert!(TaskRunner::new(plan, false).execute_tasks().is_ok());
+        assert!(std::path::Path::new(&format!("{}/after", dir)).exists());
+        assert!(!std::path::Path::new(&format!("{}/on_fail", dir)).exists());
+    }
+    #[test]
+    fn test_timeout_kills_step() {
+        let plan = TaskPlan {
+            name: "slow".to_string(),
+            mode: RunMode::Steps,
+            items: vec![PlanItem::Step(Step {
+                command: "sleep 5".to_string(),
+                timeout: Some(Duration::from_millis(200)),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let result = TaskRunner::new(plan, false).execute_tasks();
+        match result {
+            Err(ExecutionError::TimedOut(timeout)) => {
+                assert!(timeout.elapsed < Duration::from_secs(5));
+                assert_eq!(timeout.failure.exit_code, 124);
+            }
+            _ => panic!("expected the step to time out"),
+        }
+    }
+    #[test]
+    fn test_retries_until_success() {
+        let dir = scratch_dir("retries");
+        let marker = dir.join("attempted");
+        let policy: RetryPolicy = serde_yaml::from_str("{count: 2, delay: 0s}").unwrap();
+        let plan = TaskPlan {
+            name: "flaky".to_string(),
+            mode: RunMode::Steps,
+            items: vec![PlanItem::Step(Step {
+                command: format!("test -f {0} || {{ touch {0}; exit 1; }}", marker.display()),
+                retries: Some(policy),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        assert!(TaskRunner::new(plan, false).execute_tasks().is_ok());
+    }
+    #[test]
+    fn test_retries_only_on_exit_codes() {
+        let dir = scratch_dir("retries-only-on");
+        let attempts = dir.join("attempts");
+        let policy: RetryPolicy =
+            serde_yaml::from_str("{count: 3, delay: 0s, only_on: [1]}").unwrap();
+        let plan = TaskPlan {
+            name: "flaky".to_string(),
+            mode: RunMode::Steps,
+            items: vec![PlanItem::Step(Step {
+                command: format!("echo attempt >> {}; exit 2", attempts.display()),
+                retries: Some(policy),
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        assert!(TaskRunner::new(plan, false).execute_tasks().is_err());
+        let attempts = std::fs::read_to_string(attempts).unwrap();
+        assert_eq!(attempts.lines().count(), 1);
+    }
+    #[test]
+    fn test_output_mode() {
+        let plan = TaskPlan {
+            items: vec![step("echo one", false), step("echo two", false)],
+            ..Default::default()
+        };
+        let runner = TaskRunner::new(plan.to_owned(), false);
+        assert_eq!(runner.output_mode(false), OutputMode::Prefixed);
+        assert_eq!(runner.output_mode(true), OutputMode::Interactive);
+        let runner = TaskRunner::new(plan, true);
+        assert_eq!(runner.output_mode(false), OutputMode::Raw);
+        let single = TaskPlan {
+            items: vec![step("echo one", false)],
+            ..Default::default()
+        };
+        assert_eq!(
+            TaskRunner::new(single.to_owned(), false).output_mode(false),
+            OutputMode::Raw
+        );
+        // output with secrets in it is masked, which only happens with prefixes
+        let mut single = single;
+        single.secrets.register("s3cr3t");
+        let runner = TaskRunner::new(single.to_owned(), false);
+        assert_eq!(runner.output_mode(false), OutputMode::Prefixed);
+        let runner = TaskRunner::new(single, true);
+        assert_eq!(runner.output_mode(false), OutputMode::Prefixed);
+        assert_eq!(runner.output_mode(true), OutputMode::Interactive);
+    }
+    #[test]
+    fn test_script_lines_ignore_error() {
+        let items = [step("false", true), step("echo hi", false)];
+        let lines = TaskRunner::script_lines(&items, &mut 0, false);
+        assert_eq!(lines, vec!["{ false; } || true", "echo hi"]);
+    }
+    #[test]
+    fn test_script_lines_markers() {
+        let items = [
+            step("echo one", false),
+            PlanItem::Task(TaskPlan {
+                items: vec![step("echo two", false)],
+                ..Default::default()
+            }),
+        ];
+        let lines = TaskRunner::script_lines(&items, &mut 0, true);
+        assert_eq!(
+            lines,
+            vec![
+                "printf '%s\\n' 0 > \"$TASKER_STEP_MARKER\"",
+                "echo one",
+                "printf '%s\\n' 1 > \"$TASKER_STEP_MARKER\"",
+                "echo two"
+            ]
+        );
+    }
+    #[test]
+    fn test_strict_script_reports_failing_step() {
+        let plan = TaskPlan {
+            name: "outer".to_string(),
+            mode: RunMode::Script,
+            strict: true,
+            items: vec![
+                step("echo one", false),
+                PlanItem::Task(TaskPlan {
+                    name: "inner".to_string(),
+                    items: vec![PlanItem::Step(Step {
+                        task: "inner".to_string(),
+                        command: "echo $UNSET_IN_TASKER_TESTS".to_string(),
+                        template: "echo ${value}".to_string(),
+                        called_from: vec!["inner value".to_string()],
+                        ..Default::default()
+                    })],
+                    ..Default::default()
+                }),
+                step("echo never", false),
+            ],
+            ..Default::default()
+        };
+        match TaskRunner::new(plan, false).execute_tasks() {
+            Err(ExecutionError::StepFailed(failure)) => {
+                assert_eq!(failure.task, "inner");
+                assert_eq!(failure.template, "echo ${value}");
+                assert_eq!(failure.called_from, vec!["inner value"]);
+            }
+            _ => panic!("expected the unset variable to fail the script"),
+        }
+    }
+    #[test]
+    fn test_strict_step_fails_on_pipeline_and_unset() {
+        let strict_step = |command: &str| TaskPlan {
+            mode: RunMode::Steps,
+            items: vec![PlanItem::Step(Step {
+                command: command.to_string(),
+                strict: true,
+                ..Default::default()
+            })],
+            ..Default::default()
+        };
+        let runner = TaskRunner::new(strict_step("echo $UNSET_IN_TASKER_TESTS"), true);
+        assert!(runner.execute_tasks().is_err());
+        let runner = TaskRunner::new(strict_step("false; echo after"), true);
+        assert!(runner.execute_tasks().is_err());
+        let runner = TaskRunner::new(strict_step("echo fine"), true);
+        assert!(runner.execute_tasks().is_ok());
+    }
+    #[test]
+    fn test_get_all_commands_parsed_with_handlers() {
+        let runner = TaskBuilder::new(load_from_string());
+        let task = runner
+            .get_config()
+            .get_task_by_name("test-cleanup")
+            .unwrap();
+        let plan = runner
+            .get_all_commands_parsed(task.to_owned(), HashMap::new())
+            .unwrap();
+        assert!(plan.steps()[0].ignore_error);
+        assert_eq!(plan.steps()[0].timeout, Some(Duration::from_secs(30)));
+        assert_eq!(plan.steps()[0].retries.as_ref().unwrap().count, 2);
+        assert_eq!(
+            TaskPlan::flatten(&plan.on_fail)[0].command,
+            "echo Hello Foo default"
+        );
+        assert_eq!(TaskPlan::flatten(&plan.finally)[0].command, "echo cleanup");
     }
 }
@@ -0,0 +1,177 @@
+use crate::{
+    taskfile::{RetryPolicy, RunMode, Shell},
+    utils::secrets::Secrets,
+};
+use std::time::Duration;
+
+// a single shell command with all variables filled in
+#[derive(Clone, Debug, Default)]
+pub struct Step {
+    // the task the command was written in
+    pub task: String,
+    pub command: String,
+    // the command as written in the Taskfile, used to point at it when it fails
+    pub template: String,
+    // `task:` commands that expanded into this step, outermost first
+    pub called_from: Vec<String>,
+    pub ignore_error: bool,
+    pub retries: Option<RetryPolicy>,
+    pub timeout: Option<Duration>,
+    // hands the terminal to the command instead of capturing its output
+    pub interactive: bool,
+    pub shell: Shell,
+    // run with errexit, nounset and pipefail
+    pub strict: bool,
+    // set in the environment of the command, how `on_fail` commands get the failure
+    pub env: Vec<(String, String)>,
+}
+
+#[derive(Clone, Debug)]
+pub enum PlanItem {
+    Step(Step),
+    Task(TaskPlan),
+}
+
+// a task with its sub tasks expanded, ready to be handed to the runner
+#[derive(Clone, Debug, Default)]
+pub struct TaskPlan {
+    pub name: String,
+    // the args of the `task:` command that expanded into this plan
+    pub args: Vec<String>,
+    pub mode: RunMode,
+    pub ignore_error: bool,
+    // used for the whole script when the task runs in script mode
+    pub retries: Option<RetryPolicy>,
+    pub timeout: Option<Duration>,
+    pub interactive: bool,
+    pub shell: Shell,
+    pub strict: bool,
+    // asked before anything runs, variables already filled in
+    pub confirm: Option<String>,
+    pub items: Vec<PlanItem>,
+    pub on_fail: Vec<PlanItem>,
+    pub finally: Vec<PlanItem>,
+    // a `task:` call naming its task through a variable that was not set while planning,
+    // `name` and `args` are the call as far as it could be filled in and nothing is expanded
+    pub unresolved: bool,
+    // values masked in the output of the task and its sub tasks
+    pub secrets: Secrets,
+}
+
+impl TaskPlan {
+    // every step of the task in execution order, sub tasks included
+    pub fn steps(&self) -> Vec<&Step> {
+        Self::flatten(&self.items)
+    }
+    pub fn flatten(items: &[PlanItem]) -> Vec<&Step> {
+        let mut steps = vec![];
+        for item in items {
+            match item {
+                PlanItem::Step(step) => steps.push(step),
+                PlanItem::Task(plan) => steps.extend(plan.steps()),
+            }
+        }
+        steps
+    }
+    // whether the main commands, sub tasks included, can run as one script of the task's
+    // shell, which a command with an `interpreter`, `retries`, `timeout` or `interactive` of its
+    // own can not
+    pub fn fits_one_script(&self) -> bool {
+        self.fits_script_of(&self.shell)
+    }
+    fn fits_script_of(&self, shell: &Shell) -> bool {
+        self.items.iter().all(|item| match item {
+            PlanItem::Step(step) => {
+                step.shell == *shell
+                    && step.retries == self.retries
+                    && step.timeout == self.timeout
+                    && step.interactive == self.interactive
+            }
+            PlanItem::Task(plan) => plan.fits_script_of(shell),
+        })
+    }
+    // sub task plans nested in the main commands, depth first
+    pub fn sub_tasks(&self) -> Vec<&TaskPlan> {
+        let mut plans = vec![];
+        for item in &self.items {
+            if let PlanItem::Task(plan) = item {
+                plans.extend(plan.sub_tasks());
+                plans.push(plan);
+            }
+        }
+        plans
+    }
+    // steps of the task, its sub tasks and all of their handlers
+    pub fn all_steps(&self) -> Vec<&Step> {
+        let mut steps = vec![];
+        for items in [&self.items, &self.on_fail, &self.finally] {
+            for item in items {
+                match item {
+                    PlanItem::Step(step) => steps.push(step),
+                    PlanItem::Task(plan) => steps.extend(plan.all_steps()),
+                }
+            }
+        }
+        steps
+    }
+    // records the `task:` command the plan was expanded from on all of its steps
+    pub fn add_caller(&mut self, invocation: &str) {
+        for items in [&mut self.items, &mut self.on_fail, &mut self.finally] {
+            for item in items.iter_mut() {
+                match item {
+                    PlanItem::Step(step) => step.called_from.insert(0, invocation.to_string()),
+                    PlanItem::Task(plan) => plan.add_caller(invocation),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PlanItem, Step, TaskPlan};
+
+    fn step(command: &str) -> PlanItem {
+        PlanItem::Step(Step {
+            command: command.to_string(),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn test_steps_flattens_sub_tasks() {
+        let plan = TaskPlan {
+            name: "outer".to_string(),
+            items: vec![
+                step("echo one"),
+                PlanItem::Task(TaskPlan {
+                    name: "inner".to_string(),
+                    items: vec![step("echo two")],
+                    ..Default::default()
+                }),
+                step("echo three"),
+            ],
+            ..Default::default()
+        };
+        let commands: Vec<&str> = plan.steps().iter().map(|s| s.command.as_str()).collect();
+        assert_eq!(commands, vec!["echo one", "echo two", "echo three"]);
+        assert_eq!(plan.sub_tasks()[0].name, "inner");
+    }
+    #[test]
+    fn test_add_caller() {
+        let mut plan = TaskPlan {
+            items: vec![
+                step("echo one"),
+                PlanItem::Task(TaskPlan {
+                    items: vec![step("echo two")],
+                    ..Default::default()
+                }),
+            ],
+            ..Default::default()
+        };
+        plan.add_caller("inner");
+        plan.add_caller("outer");
+        assert_eq!(plan.steps()[0].called_from, vec!["outer", "inner"]);
+        assert_eq!(plan.steps()[1].called_from, vec!["outer", "inner"]);
+    }
+}
@@ -0,0 +1,175 @@
+use super::{errors::ExecutionError, signals};
+use crate::utils::secrets::Secrets;
+use libc::{SIGINT, SIGKILL};
+use std::{
+    io::{BufRead, BufReader, IsTerminal, Read},
+    os::unix::process::CommandExt,
+    process::{Child, Command, ExitStatus, Stdio},
+    thread,
+    time::{Duration, Instant},
+};
+
+pub const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// how long a step gets to exit after a forwarded signal before it is killed
+const KILL_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+// where the input and output of a step go
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputMode {
+    // output is captured and printed line by line behind a `>` marker
+    Prefixed,
+    // output goes straight to the terminal so tools that check for a tty keep their colors
+    Raw,
+    // the step shares the terminal with tasker, stdin included, and gets Ctrl-C directly,
+    // so its output can not be masked
+    Interactive,
+}
+
+// how a spawned command came to an end
+pub enum Finished {
+    Exited(ExitStatus),
+    TimedOut(Duration),
+    // ignored a forwarded signal past the grace period
+    Killed,
+}
+
+pub fn call_command(
+    mut command: Command,
+    timeout: Option<Duration>,
+    output: OutputMode,
+    secrets: &Secrets,
+) -> Result<Finished, ExecutionError> {
+    if output == OutputMode::Interactive {
+        return call_interactive(command, timeout);
+    }
+    // a background process group reading from the terminal would be stopped, so only pass
+    // stdin along when it is a pipe or a file
+    let stdin = match std::io::stdin().is_terminal() {
+        true => Stdio::null(),
+        false => Stdio::inherit(),
+    };
+    // each step leads its own process group so everything it started can be killed together
+    command.stdin(stdin).process_group(0);
+    if output == OutputMode::Prefixed {
+        command.stdout(Stdio::piped()).stderr(Stdio::piped());
+    }
+    let mut cmd = command.spawn()?;
+    let stdout_printer = cmd.stdout.take().map(|stdout| {
+        let secrets = secrets.to_owned();
+        thread::spawn(move || print_lines(stdout, &secrets))
+    });
+    // stderr is only shown when the command fails
+    let stderr_collector = cmd
+        .stderr
+        .take()
+        .map(|stderr| thread::spawn(move || BufReader::new(stderr).lines().collect::<Vec<_>>()));
+
+    let finished = wait_for_group(&mut cmd, timeout)?;
+
+    if let Some(printer) = stdout_printer {
+        let _ = printer.join();
+    }
+    if let Some(collector) = stderr_collector {
+        let stderr_lines = collector.join().unwrap_or_default();
+        if !matches!(finished, Finished::Exited(status) if status.success()) {
+            for stderr_line in stderr_lines {
+                match stderr_line {
+                    Ok(line) => {
+                        println!("\x1b[31m>\x1b[0m {}", secrets.mask(&line));
+                    }
+                    Err(e) => {
+                        println!("Error reading stderr: {}", e);
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    Ok(finished)
+}
+
+fn print_lines(stdout: impl Read, secrets: &Secrets) {
+    for stdout_line in BufReader::new(stdout).lines() {
+        match stdout_line {
+            Ok(line) => {
+                println!("\x1b[32m>\x1b[0m {}", secrets.mask(&line));
+            }
+            Err(e) => {
+                println!("Error reading stdout: {}", e);
+                break;
+            }
+        }
+    }
+}
+
+fn wait_for_group(cmd: &mut Child, timeout: Option<Duration>) -> Result<Finished, ExecutionError> {
+    let started = Instant::now();
+    let signals_before = signals::count();
+    let mut forwarded_at: Option<Instant> = None;
+    let mut exit_status = None;
+    loop {
+        if exit_status.is_none() {
+            exit_status = cmd.try_wait()?;
+        }
+        match (exit_status, forwarded_at) {
+            (Some(status), None) => return Ok(Finished::Exited(status)),
+            // once a signal was forwarded, wait for everything the step started
+            (Some(status), Some(_)) if !signals::group_alive(cmd) => {
+                return Ok(Finished::Exited(status))
+            }
+            _ => {}
+        }
+        if timeout.is_some_and(|limit| started.elapsed() >= limit) {
+            signals::signal_group(cmd, SIGKILL);
+            cmd.wait()?;
+            return Ok(Finished::TimedOut(started.elapsed()));
+        }
+        // the step is not in the terminal's process group, so pass signals along ourselves
+        if signals::count() > signals_before {
+            match forwarded_at {
+                None => {
+                    signals::signal_group(cmd, signals::received().unwrap_or(SIGINT));
+                    forwarded_at = Some(Instant::now());
+                }
+                Some(at) if at.elapsed() >= KILL_GRACE_PERIOD => {
+                    eprintln!(
+                        "step did not stop within {}s, killing it",
+                        KILL_GRACE_PERIOD.as_secs()
+                    );
+                    signals::signal_group(cmd, SIGKILL);
+                    cmd.wait()?;
+                    return Ok(Finished::Killed);
+                }
+                Some(_) => {}
+            }
+        }
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+// interactive steps stay in the terminal's foreground process group so they can read from it
+fn call_interactive(
+    mut command: Command,
+    timeout: Option<Duration>,
+) -> Result<Finished, ExecutionError> {
+    let received_before = signals::received();
+    let mut cmd = command.spawn()?;
+    let started = Instant::now();
+    let finished = loop {
+        if let Some(status) = cmd.try_wait()? {
+            break Finished::Exited(status);
+        }
+        if timeout.is_some_and(|limit| started.elapsed() >= limit) {
+            cmd.kill()?;
+            cmd.wait()?;
+            break Finished::TimedOut(started.elapsed());
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    // Ctrl-C reached the step too, if it handled it and carried on then so do we
+    if matches!(finished, Finished::Exited(status) if status.success()) {
+        signals::restore(received_before);
+    }
+    Ok(finished)
+}
@@ -0,0 +1,78 @@
+use libc::{SIGINT, SIGTERM};
+use std::{
+    process::Child,
+    sync::{
+        atomic::{AtomicI32, AtomicUsize, Ordering},
+        Once,
+    },
+};
+
+static INSTALL: Once = Once::new();
+static LAST_SIGNAL: AtomicI32 = AtomicI32::new(0);
+static SIGNAL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+// catch SIGINT and SIGTERM instead of dying with the child so `finally` commands still get to run
+pub fn install() {
+    INSTALL.call_once(|| {
+        for signal in [SIGINT, SIGTERM] {
+            // only touches atomics, which is all a signal handler may safely do
+            let handler = move || {
+                LAST_SIGNAL.store(signal, Ordering::SeqCst);
+                SIGNAL_COUNT.fetch_add(1, Ordering::SeqCst);
+            };
+            // without the handler we only lose cleanup on interrupt, so this is not fatal
+            let _ = unsafe { signal_hook::low_level::register(signal, handler) };
+        }
+    });
+}
+
+// the most recent signal received, if any
+pub fn received() -> Option<i32> {
+    match LAST_SIGNAL.load(Ordering::SeqCst) {
+        0 => None,
+        signal => Some(signal),
+    }
+}
+
+// forget signals that a step handled on its own
+pub fn restore(previous: Option<i32>) {
+    LAST_SIGNAL.store(previous.unwrap_or(0), Ordering::SeqCst);
+}
+
+pub fn interrupted() -> bool {
+    received().is_some()
+}
+
+// lets a running step tell signals that arrived while it ran apart from earlier ones
+pub fn count() -> usize {
+    SIGNAL_COUNT.load(Ordering::SeqCst)
+}
+
+pub fn name(signal: i32) -> String {
+    match signal {
+        SIGINT => "SIGINT".to_string(),
+        SIGTERM => "SIGTERM".to_string(),
+        _ => format!("signal {}", signal),
+    }
+}
+
+// steps lead their own process group, so a negative pid reaches everything they started
+pub fn signal_group(child: &Child, signal: i32) {
+    unsafe {
+        libc::kill(-(child.id() as i32), signal);
+    }
+}
+
+pub fn group_alive(child: &Child) -> bool {
+    unsafe { libc::kill(-(child.id() as i32), 0) == 0 }
+}
+
+#[cfg(test)]
+mod tests {
+    #[test]
+    fn test_name() {
+        assert_eq!(super::name(libc::SIGINT), "SIGINT");
+        assert_eq!(super::name(libc::SIGTERM), "SIGTERM");
+        assert_eq!(super::name(libc::SIGHUP), "signal 1");
+    }
+}
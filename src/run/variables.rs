@@ -0,0 +1,89 @@
+use super::TaskBuilder;
+use crate::{
+    taskfile::Location,
+    utils::{iters::upsert_into_hash_map, secrets::Secrets},
+};
+use std::{collections::BTreeMap, fmt};
+
+// where a variable's value came from, in the order they are loaded, later ones win
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Layer {
+    Default,
+    Secret,
+    Context,
+    Env,
+    CommandLine,
+}
+impl fmt::Display for Layer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Layer::Default => write!(f, "default"),
+            Layer::Secret => write!(f, "secret"),
+            Layer::Context => write!(f, "context"),
+            Layer::Env => write!(f, "env"),
+            Layer::CommandLine => write!(f, "command line"),
+        }
+    }
+}
+
+// one value a variable was given while loading
+#[derive(Clone, Debug, PartialEq)]
+pub struct VariableSource {
+    pub layer: Layer,
+    pub value: String,
+    // what within the layer set it, e.g. the context or environment variable
+    pub origin: Option<String>,
+    pub location: Option<Location>,
+}
+impl VariableSource {
+    pub fn new(layer: Layer, value: String) -> Self {
+        Self {
+            layer,
+            value,
+            origin: None,
+            location: None,
+        }
+    }
+    pub fn with_origin(mut self, origin: String) -> Self {
+        self.origin = Some(origin);
+        self
+    }
+    pub fn with_location(mut self, location: Option<Location>) -> Self {
+        self.location = location;
+        self
+    }
+}
+impl fmt::Display for VariableSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.layer)?;
+        if let Some(origin) = &self.origin {
+            write!(f, " {}", origin)?;
+        }
+        if let Some(location) = &self.location {
+            write!(f, " ({})", location)?;
+        }
+        Ok(())
+    }
+}
+
+impl TaskBuilder {
+    pub(super) fn set_variable(&mut self, name: String, source: VariableSource) {
+        upsert_into_hash_map(
+            name.to_owned(),
+            source.value.to_owned(),
+            &mut self.variable_lookup,
+        );
+        self.variable_sources.entry(name).or_default().push(source);
+    }
+    // every variable of the planned task with each value it was given, the last one is used
+    pub fn variable_sources(&self) -> &BTreeMap<String, Vec<VariableSource>> {
+        return &self.variable_sources;
+    }
+    pub fn context_names(&self) -> &[String] {
+        return &self.context_names;
+    }
+    // the secret values planning came across, for masking what is printed about them
+    pub(crate) fn secrets(&self) -> &Secrets {
+        return &self.secrets;
+    }
+}
@@ -2,6 +2,8 @@ use std::fmt;
 
 use serde::{de::Visitor, Deserialize, Deserializer};
 
+use super::settings::{HumanDuration, RetryPolicy, Shell};
+
 #[derive(Clone, Deserialize, Debug)]
 #[serde(rename_all = "lowercase")]
 pub enum CommandTypes {
@@ -25,6 +27,31 @@ impl fmt::Display for CommandTypes {
 pub struct TaskCmd {
     pub key: CommandTypes,
     pub value: String,
+    // keep going with the rest of the task when this command fails
+    pub ignore_error: bool,
+    pub retries: Option<RetryPolicy>,
+    pub timeout: Option<HumanDuration>,
+    // hand the terminal to the command, for prompts and tools like `psql`
+    pub interactive: bool,
+    // `shell` already names the command type, so the per command override goes by another name
+    pub interpreter: Option<Shell>,
+}
+impl TaskCmd {
+    fn new(key: CommandTypes, value: &str) -> TaskCmd {
+        return TaskCmd {
+            key,
+            value: value.to_string(),
+            ignore_error: false,
+            retries: None,
+            timeout: None,
+            interactive: false,
+            interpreter: None,
+        };
+    }
+    // for tasks tasker writes itself, e.g. from a provider or an included Makefile
+    pub(super) fn shell(command: &str) -> TaskCmd {
+        return TaskCmd::new(CommandTypes::Shell("shell".to_string()), command);
+    }
 }
 impl<'de> Deserialize<'de> for TaskCmd {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
@@ -46,8 +73,37 @@ impl<'de> Deserialize<'de> for TaskCmd {
             {
                 let mut key = None;
                 let mut value = None;
+                let mut ignore_error = false;
+                let mut retries = None;
+                let mut timeout = None;
+                let mut interactive = false;
+                let mut interpreter = None;
 
                 while let Some(k) = map.next_key::<String>()? {
+                    // options can sit next to the command, everything else is the command itself
+                    match k.as_str() {
+                        "ignore_error" => {
+                            ignore_error = map.next_value::<bool>()?;
+                            continue;
+                        }
+                        "retries" => {
+                            retries = Some(map.next_value::<RetryPolicy>()?);
+                            continue;
+                        }
+                        "timeout" => {
+                            timeout = Some(map.next_value::<HumanDuration>()?);
+                            continue;
+                        }
+                        "interactive" => {
+                            interactive = map.next_value::<bool>()?;
+                            continue;
+                        }
+                        "interpreter" => {
+                            interpreter = Some(map.next_value::<Shell>()?);
+                            continue;
+                        }
+                        _ => {}
+                    }
                     let v = map.next_value::<String>()?;
 
                     if key.is_some() {
@@ -73,7 +129,15 @@ impl<'de> Deserialize<'de> for TaskCmd {
                     }
                 };
 
-                Ok(TaskCmd { key, value })
+                Ok(TaskCmd {
+                    key,
+                    value,
+                    ignore_error,
+                    retries,
+                    timeout,
+                    interactive,
+                    interpreter,
+                })
             }
         }
 
@@ -81,17 +145,76 @@ impl<'de> Deserialize<'de> for TaskCmd {
     }
 }
 
+// `prompt: true` asks with a generated question, a string is asked as written
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ArgPrompt {
+    Enabled(bool),
+    Question(String),
+}
+
 // cmd arg stanzas
 #[derive(Deserialize, Clone)]
 pub struct CmdArg {
     pub name: String,
     #[serde(default)]
     pub default: Option<String>,
+    // checked when a value is typed in at a prompt
     #[serde(rename = "type")]
     pub arg_type: String,
+    // ask for the value on a terminal when it is missing
+    #[serde(default)]
+    pub prompt: Option<ArgPrompt>,
+    #[serde(default)]
+    pub choices: Vec<String>,
+    // never echoed when typed in
+    #[serde(default)]
+    pub secret: bool,
 }
 impl CmdArg {
-    fn is_required(&self) -> bool {
+    // a plain string arg, optionally with a default
+    pub(super) fn string(name: &str, default: Option<String>) -> CmdArg {
+        return CmdArg {
+            name: name.to_string(),
+            default,
+            arg_type: "string".to_string(),
+            prompt: None,
+            choices: vec![],
+            secret: false,
+        };
+    }
+    // the question to ask when the value is missing, `--interactive` asks for every arg that
+    // does not opt out
+    pub fn prompt_question(&self, interactive: bool) -> Option<String> {
+        return match &self.prompt {
+            Some(ArgPrompt::Question(question)) => Some(question.to_owned()),
+            Some(ArgPrompt::Enabled(false)) => None,
+            Some(ArgPrompt::Enabled(true)) => Some(self.name.to_owned()),
+            None if interactive => Some(self.name.to_owned()),
+            None => None,
+        };
+    }
+    // checks a value against the declared type and choices
+    pub fn validate(&self, value: &str) -> Result<(), String> {
+        if !self.choices.is_empty() && !self.choices.iter().any(|choice| choice == value) {
+            return Err(format!(
+                "`{}` is not one of {}",
+                value,
+                self.choices.join(", ")
+            ));
+        }
+        let valid = match self.arg_type.to_lowercase().as_str() {
+            "int" | "integer" => value.parse::<i64>().is_ok(),
+            "float" | "number" => value.parse::<f64>().is_ok(),
+            "bool" | "boolean" => matches!(value, "true" | "false"),
+            _ => true,
+        };
+        if !valid {
+            return Err(format!("`{}` is not a valid {}", value, self.arg_type));
+        }
+        return Ok(());
+    }
+    pub fn is_required(&self) -> bool {
         if self.default.is_none() {
             return true;
         }
@@ -99,7 +222,7 @@ impl CmdArg {
     }
     pub(super) fn get_clap_arg(&self) -> clap::Arg {
         let name_owned = self.name.to_owned();
-        let env_name = format!("TASKER_{}", name_owned.clone().to_uppercase());
+        let env_name = self.env_var();
         if !self.is_required() {
             let default = self.default.to_owned().unwrap();
             return clap::Arg::new(&name_owned)
@@ -109,6 +232,10 @@ impl CmdArg {
             return clap::Arg::new(&name_owned).env(env_name).required(true);
         }
     }
+    // the environment variable the value can be passed in
+    pub fn env_var(&self) -> String {
+        return format!("TASKER_{}", self.name.to_uppercase());
+    }
     pub fn get_name(&self) -> &str {
         return &self.name;
     }
@@ -124,13 +251,45 @@ mod tests {
             name: "test".to_string(),
             default: Some("default".to_string()),
             arg_type: "string".to_string(),
+            prompt: None,
+            choices: vec![],
+            secret: false,
         };
         let clap_arg = arg.get_clap_arg();
         assert_eq!(clap_arg.get_id(), "test");
-        assert_eq!(clap_arg.is_required_set(), false);
+        assert!(!clap_arg.is_required_set());
         assert_eq!(clap_arg.get_default_values(), &["default"]);
     }
 
+    #[test]
+    fn test_cmd_arg_prompt_question() {
+        let yaml = "{name: region, type: string, prompt: Which region?}";
+        let arg: super::CmdArg = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(arg.prompt_question(false).unwrap(), "Which region?");
+        let arg: super::CmdArg = serde_yaml::from_str("{name: region, type: string}").unwrap();
+        assert_eq!(arg.prompt_question(false), None);
+        assert_eq!(arg.prompt_question(true).unwrap(), "region");
+        let yaml = "{name: token, type: string, prompt: false}";
+        let arg: super::CmdArg = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(arg.prompt_question(true), None);
+    }
+    #[test]
+    fn test_cmd_arg_validate() {
+        let yaml = "{name: count, type: int}";
+        let arg: super::CmdArg = serde_yaml::from_str(yaml).unwrap();
+        assert!(arg.validate("3").is_ok());
+        assert_eq!(
+            arg.validate("three").unwrap_err(),
+            "`three` is not a valid int"
+        );
+        let yaml = "{name: env, type: string, choices: [staging, prod]}";
+        let arg: super::CmdArg = serde_yaml::from_str(yaml).unwrap();
+        assert!(arg.validate("prod").is_ok());
+        assert_eq!(
+            arg.validate("dev").unwrap_err(),
+            "`dev` is not one of staging, prod"
+        );
+    }
     #[test]
     fn test_display_command_types() {
         let task = super::CommandTypes::Task("task".to_string());
@@ -168,6 +327,9 @@ mod tests {
             name: "test".to_string(),
             default: Some("default".to_string()),
             arg_type: "string".to_string(),
+            prompt: None,
+            choices: vec![],
+            secret: false,
         };
         assert_eq!(arg.get_name(), "test");
     }
@@ -177,10 +339,46 @@ mod tests {
             name: "test".to_string(),
             default: Some("default".to_string()),
             arg_type: "string".to_string(),
+            prompt: None,
+            choices: vec![],
+            secret: false,
         };
         assert_eq!(arg.get_default(), Some("default"));
     }
     #[test]
+    fn test_deserialize_task_cmd_ignore_error() {
+        let yaml = r#"
+        shell: "false"
+        ignore_error: true
+        "#;
+        let task_cmd: super::TaskCmd = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(task_cmd.value, "false");
+        assert!(task_cmd.ignore_error);
+        let task_cmd: super::TaskCmd = serde_yaml::from_str("shell: echo").unwrap();
+        assert!(!task_cmd.ignore_error);
+    }
+    #[test]
+    fn test_deserialize_task_cmd_retries_and_timeout() {
+        let yaml = r#"
+        shell: curl https://example.com
+        retries: 2
+        timeout: 90s
+        "#;
+        let task_cmd: super::TaskCmd = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(task_cmd.retries.unwrap().count, 2);
+        assert_eq!(task_cmd.timeout.unwrap().0.as_secs(), 90);
+        assert!(!task_cmd.interactive);
+        let task_cmd: super::TaskCmd =
+            serde_yaml::from_str("{shell: psql, interactive: true}").unwrap();
+        assert!(task_cmd.interactive);
+    }
+    #[test]
+    fn test_deserialize_task_cmd_interpreter() {
+        let task_cmd: super::TaskCmd =
+            serde_yaml::from_str("{shell: print(1), interpreter: python3 -c}").unwrap();
+        assert_eq!(task_cmd.interpreter.unwrap().program, "python3");
+    }
+    #[test]
     fn test_deserialize_task_cmd_unknown_key() {
         let yaml = r#"
         test: "test"
@@ -0,0 +1,225 @@
+use super::errors::TaskfileError;
+use crate::utils::errors::ErrWithMessage;
+use serde::Deserialize;
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+// a context value, either plain or `{value: ..., secret: true}` to keep it out of output
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum ContextValue {
+    Plain(String),
+    Marked {
+        value: String,
+        #[serde(default)]
+        secret: bool,
+    },
+}
+impl ContextValue {
+    pub fn value(&self) -> &str {
+        match self {
+            ContextValue::Plain(value) => value,
+            ContextValue::Marked { value, .. } => value,
+        }
+    }
+    pub fn is_secret(&self) -> bool {
+        matches!(self, ContextValue::Marked { secret: true, .. })
+    }
+}
+
+// a named set of variables, optionally built on top of other contexts
+#[derive(Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct TaskContext {
+    // parents are applied in order, so later ones win
+    #[serde(default)]
+    pub extends: Vec<String>,
+    // tasks only run under it after typing its name, carries over to contexts extending it
+    #[serde(default)]
+    pub protected: bool,
+    #[serde(flatten)]
+    pub values: HashMap<String, ContextValue>,
+    // the `contexts/<name>.yaml` file it was loaded from, none when it is in the Taskfile
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
+}
+
+fn context_error(code: &str, message: String) -> TaskfileError {
+    TaskfileError::InvalidSetting(ErrWithMessage {
+        code: code.to_string(),
+        messages: vec![message],
+    })
+}
+
+// the variables of a context with everything it extends filled in
+pub fn resolve_context(
+    contexts: &HashMap<String, TaskContext>,
+    name: &str,
+) -> Result<HashMap<String, String>, TaskfileError> {
+    let mut chain = vec![];
+    return resolve_with_chain(contexts, name, &mut chain);
+}
+
+fn resolve_with_chain(
+    contexts: &HashMap<String, TaskContext>,
+    name: &str,
+    chain: &mut Vec<String>,
+) -> Result<HashMap<String, String>, TaskfileError> {
+    if chain.iter().any(|seen| seen == name) {
+        chain.push(name.to_string());
+        return Err(context_error(
+            "CONTEXT_CYCLE",
+            format!(
+                "contexts extend each other in a loop: {}",
+                chain.join(" -> ")
+            ),
+        ));
+    }
+    let context = match contexts.get(name) {
+        Some(context) => context,
+        None => {
+            let message = match chain.last() {
+                Some(child) => format!(
+                    "context `{}` extends `{}`, which does not exist",
+                    child, name
+                ),
+                None => format!("context `{}` does not exist", name),
+            };
+            return Err(context_error("UNKNOWN_CONTEXT", message));
+        }
+    };
+    chain.push(name.to_string());
+    let mut values = HashMap::new();
+    for parent in &context.extends {
+        values.extend(resolve_with_chain(contexts, parent, chain)?);
+    }
+    chain.pop();
+    for (key, value) in &context.values {
+        values.insert(key.to_owned(), value.value().to_string());
+    }
+    return Ok(values);
+}
+
+// the context a resolved value comes from, either the context itself or the last parent
+// that sets it
+pub fn defining_context(
+    contexts: &HashMap<String, TaskContext>,
+    name: &str,
+    key: &str,
+) -> Option<String> {
+    let context = contexts.get(name)?;
+    if context.values.contains_key(key) {
+        return Some(name.to_string());
+    }
+    return context
+        .extends
+        .iter()
+        .rev()
+        .find_map(|parent| defining_context(contexts, parent, key));
+}
+
+// keys marked secret in the context or anything it extends
+pub fn secret_keys(contexts: &HashMap<String, TaskContext>, name: &str) -> HashSet<String> {
+    let mut keys = HashSet::new();
+    if let Some(context) = contexts.get(name) {
+        for parent in &context.extends {
+            keys.extend(secret_keys(contexts, parent));
+        }
+        for (key, value) in &context.values {
+            if value.is_secret() {
+                keys.insert(key.to_owned());
+            }
+        }
+    }
+    return keys;
+}
+
+pub fn is_protected(contexts: &HashMap<String, TaskContext>, name: &str) -> bool {
+    return match contexts.get(name) {
+        Some(context) => {
+            context.protected
+                || context
+                    .extends
+                    .iter()
+                    .any(|parent| is_protected(contexts, parent))
+        }
+        None => false,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{defining_context, is_protected, resolve_context, secret_keys, TaskContext};
+    use std::collections::HashMap;
+
+    fn contexts(yaml: &str) -> HashMap<String, TaskContext> {
+        serde_yaml::from_str(yaml).unwrap()
+    }
+
+    #[test]
+    fn test_resolve_context_extends() {
+        let contexts = contexts(
+            r#"
+            base: {region: us-east-1, replicas: "1"}
+            large: {replicas: "5", size: xl}
+            prod: {extends: [base, large], region: eu-west-1}
+            "#,
+        );
+        let prod = resolve_context(&contexts, "prod").unwrap();
+        assert_eq!(prod.get("region").unwrap(), "eu-west-1");
+        assert_eq!(prod.get("replicas").unwrap(), "5");
+        assert_eq!(prod.get("size").unwrap(), "xl");
+        assert!(!prod.contains_key("extends"));
+        let origin = |key| defining_context(&contexts, "prod", key);
+        assert_eq!(origin("region").unwrap(), "prod");
+        assert_eq!(origin("replicas").unwrap(), "large");
+        assert_eq!(origin("size").unwrap(), "large");
+        assert_eq!(origin("nope"), None);
+    }
+    #[test]
+    fn test_is_protected() {
+        let contexts = contexts(
+            r#"
+            prod: {protected: true, region: us-east-1}
+            prod-eu: {extends: [prod], region: eu-west-1}
+            staging: {region: us-east-1}
+            "#,
+        );
+        assert!(is_protected(&contexts, "prod"));
+        assert!(is_protected(&contexts, "prod-eu"));
+        assert!(!is_protected(&contexts, "staging"));
+        assert!(!resolve_context(&contexts, "prod")
+            .unwrap()
+            .contains_key("protected"));
+    }
+    #[test]
+    fn test_secret_keys() {
+        let contexts = contexts(
+            r#"
+            base: {token: {value: abc, secret: true}, region: us-east-1}
+            prod: {extends: [base], token: def, password: {value: hunter2, secret: true}}
+            "#,
+        );
+        let prod = resolve_context(&contexts, "prod").unwrap();
+        assert_eq!(prod.get("token").unwrap(), "def");
+        assert_eq!(prod.get("password").unwrap(), "hunter2");
+        let mut keys: Vec<String> = secret_keys(&contexts, "prod").into_iter().collect();
+        keys.sort();
+        assert_eq!(keys, vec!["password", "token"]);
+    }
+    #[test]
+    fn test_resolve_context_cycle() {
+        let contexts = contexts("a: {extends: [b]}\nb: {extends: [a]}");
+        let error = resolve_context(&contexts, "a").unwrap_err();
+        assert!(error.to_string().contains("a -> b -> a"));
+    }
+    #[test]
+    fn test_resolve_context_missing_parent() {
+        let contexts = contexts("a: {extends: [nope]}");
+        let error = resolve_context(&contexts, "a").unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("context `a` extends `nope`, which does not exist"));
+    }
+}
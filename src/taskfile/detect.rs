@@ -0,0 +1,270 @@
+use std::path::Path;
+
+// a task tasker can offer for a project file it recognises, e.g. `cargo test` for Cargo.toml
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedTask {
+    pub name: String,
+    pub description: String,
+    pub command: String,
+}
+
+// the tasks one kind of project file brings, `namespace` is the tool that runs them
+#[derive(Debug, Clone, PartialEq)]
+pub struct Detected {
+    pub namespace: &'static str,
+    pub file: &'static str,
+    pub tasks: Vec<DetectedTask>,
+}
+
+// reads the project file in a directory, None when it is not there
+type Provider = fn(&Path) -> Option<Vec<DetectedTask>>;
+
+fn task(name: &str, description: &str, command: &str) -> DetectedTask {
+    return DetectedTask {
+        name: name.to_string(),
+        description: description.to_string(),
+        command: command.to_string(),
+    };
+}
+
+fn cargo(dir: &Path) -> Option<Vec<DetectedTask>> {
+    let manifest = std::fs::read_to_string(dir.join("Cargo.toml")).ok()?;
+    let mut tasks = vec![
+        task("build", "build with cargo", "cargo build"),
+        task("test", "run the tests with cargo", "cargo test"),
+        task(
+            "lint",
+            "lint with clippy",
+            "cargo clippy --all-targets -- -D warnings",
+        ),
+    ];
+    // a virtual workspace has nothing to run
+    if manifest.contains("[package]") {
+        tasks.push(task("run", "run the binary with cargo", "cargo run"));
+    }
+    return Some(tasks);
+}
+
+fn npm(dir: &Path) -> Option<Vec<DetectedTask>> {
+    let manifest = std::fs::read_to_string(dir.join("package.json")).ok()?;
+    let manifest: serde_json::Value = serde_json::from_str(&manifest).ok()?;
+    let runner = match () {
+        _ if dir.join("pnpm-lock.yaml").exists() => "pnpm",
+        _ if dir.join("yarn.lock").exists() => "yarn",
+        _ => "npm",
+    };
+    let mut tasks = vec![task(
+        "install",
+        "install the dependencies",
+        &format!("{} install", runner),
+    )];
+    if let Some(scripts) = manifest
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+    {
+        for name in scripts.keys() {
+            tasks.push(task(
+                name,
+                &format!("run the `{}` script of package.json", name),
+                &format!("{} run {}", runner, name),
+            ));
+        }
+    }
+    return Some(tasks);
+}
+
+fn python(dir: &Path) -> Option<Vec<DetectedTask>> {
+    let manifest = std::fs::read_to_string(dir.join("pyproject.toml")).ok()?;
+    let (runner, build) = match () {
+        _ if manifest.contains("[tool.poetry]") => ("poetry run ", "poetry build"),
+        _ if dir.join("uv.lock").exists() => ("uv run ", "uv build"),
+        _ => ("", "python -m build"),
+    };
+    return Some(vec![
+        task("build", "build the package", build),
+        task(
+            "test",
+            "run the tests with pytest",
+            &format!("{}pytest", runner),
+        ),
+        task("lint", "lint with ruff", &format!("{}ruff check .", runner)),
+    ]);
+}
+
+fn go(dir: &Path) -> Option<Vec<DetectedTask>> {
+    if !dir.join("go.mod").is_file() {
+        return None;
+    }
+    return Some(vec![
+        task("build", "build every package", "go build ./..."),
+        task("test", "run the tests of every package", "go test ./..."),
+        task("lint", "vet every package", "go vet ./..."),
+        task("run", "run the main package", "go run ."),
+    ]);
+}
+
+// explicit targets only, pattern rules, variables and special targets are left out
+fn make_targets(makefile: &str) -> Vec<String> {
+    let mut targets: Vec<String> = vec![];
+    for line in makefile.lines() {
+        if line.starts_with(|c: char| c.is_whitespace() || c == '.' || c == '#') {
+            continue;
+        }
+        let (names, rest) = match line.split_once(':') {
+            Some(split) => split,
+            None => continue,
+        };
+        // `x := y` and `x ::= y` are assignments
+        if rest.starts_with('=') || rest.starts_with(":=") || names.contains('=') {
+            continue;
+        }
+        for name in names.split_whitespace() {
+            if name.contains(['%', '$']) || targets.iter().any(|target| target == name) {
+                continue;
+            }
+            targets.push(name.to_string());
+        }
+    }
+    return targets;
+}
+
+fn make(dir: &Path) -> Option<Vec<DetectedTask>> {
+    let makefile = std::fs::read_to_string(dir.join("Makefile")).ok()?;
+    let tasks = make_targets(&makefile)
+        .iter()
+        .map(|target| {
+            task(
+                target,
+                &format!("make the `{}` target", target),
+                &format!("make {}", target),
+            )
+        })
+        .collect();
+    return Some(tasks);
+}
+
+// image names must be lowercase
+fn image_name(dir: &Path) -> String {
+    let name = dir
+        .canonicalize()
+        .ok()
+        .and_then(|dir| {
+            dir.file_name()
+                .map(|name| name.to_string_lossy().to_string())
+        })
+        .unwrap_or_default()
+        .to_lowercase()
+        .replace(
+            |c: char| !c.is_ascii_alphanumeric() && c != '-' && c != '_',
+            "-",
+        );
+    return match name.trim_matches('-').is_empty() {
+        true => "app".to_string(),
+        false => name.trim_matches('-').to_string(),
+    };
+}
+
+fn docker(dir: &Path) -> Option<Vec<DetectedTask>> {
+    if !dir.join("Dockerfile").is_file() {
+        return None;
+    }
+    let image = image_name(dir);
+    return Some(vec![
+        task(
+            "build",
+            "build the docker image",
+            &format!("docker build -t {} .", image),
+        ),
+        task(
+            "run",
+            "run the docker image",
+            &format!("docker run --rm -it {}", image),
+        ),
+    ]);
+}
+
+fn compose(dir: &Path) -> Option<Vec<DetectedTask>> {
+    let files = [
+        "docker-compose.yml",
+        "docker-compose.yaml",
+        "compose.yml",
+        "compose.yaml",
+    ];
+    if !files.iter().any(|file| dir.join(file).is_file()) {
+        return None;
+    }
+    return Some(vec![
+        task("up", "start the compose services", "docker compose up"),
+        task("down", "stop the compose services", "docker compose down"),
+        task(
+            "build",
+            "build the compose services",
+            "docker compose build",
+        ),
+    ]);
+}
+
+// in a fixed order so the output is stable
+const PROVIDERS: [(&str, &str, Provider); 7] = [
+    ("cargo", "Cargo.toml", cargo),
+    ("npm", "package.json", npm),
+    ("python", "pyproject.toml", python),
+    ("go", "go.mod", go),
+    ("make", "Makefile", make),
+    ("docker", "Dockerfile", docker),
+    ("compose", "docker-compose.yml", compose),
+];
+
+// what the `providers` key of a Taskfile accepts
+pub fn provider_names() -> Vec<&'static str> {
+    return PROVIDERS
+        .iter()
+        .map(|(namespace, _, _)| *namespace)
+        .collect();
+}
+
+// every kind of project found in `dir`
+pub fn detect(dir: &Path) -> Vec<Detected> {
+    return PROVIDERS
+        .into_iter()
+        .filter_map(|(namespace, file, provider)| {
+            provider(dir).map(|tasks| Detected {
+                namespace,
+                file,
+                tasks,
+            })
+        })
+        .filter(|detected| !detected.tasks.is_empty())
+        .collect();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{detect, make_targets};
+
+    #[test]
+    fn test_make_targets() {
+        let makefile = "CC := gcc\n.PHONY: build test\nbuild test: deps\n\tgcc x.c\n%.o: %.c\n\t$(CC)\ndeps:\n";
+        assert_eq!(make_targets(makefile), vec!["build", "test", "deps"]);
+    }
+    #[test]
+    fn test_detect() {
+        let dir = std::env::temp_dir().join(format!("tasker-detect-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("go.mod"), "module example.com/x\n").unwrap();
+        std::fs::write(dir.join("Dockerfile"), "FROM scratch\n").unwrap();
+        std::fs::write(
+            dir.join("package.json"),
+            r#"{"scripts": {"start": "node .", "build": "tsc"}}"#,
+        )
+        .unwrap();
+        let detected = detect(&dir);
+        let _ = std::fs::remove_dir_all(&dir);
+        let namespaces: Vec<_> = detected.iter().map(|d| d.namespace).collect();
+        assert_eq!(namespaces, vec!["npm", "go", "docker"]);
+        assert_eq!(detected[0].tasks[1].command, "npm run build");
+        assert!(detected[2].tasks[0]
+            .command
+            .starts_with("docker build -t tasker-detect-"));
+    }
+}
@@ -0,0 +1,161 @@
+use super::{errors::TaskfileError, taskfile::Taskfile};
+use crate::utils::errors::ErrWithMessage;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+// ends a binary built with `tasker build-cli`, preceded by the payload length
+const MAGIC: &[u8; 8] = b"TASKRCLI";
+const TRAILER: u64 = 16;
+
+// what `tasker build-cli` appends to a copy of tasker
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct Bundle {
+    // the name the CLI runs under
+    pub name: String,
+    // the Taskfile with its `contexts/*.yaml` merged in
+    pub taskfile: String,
+}
+
+fn bundle_error(message: String) -> TaskfileError {
+    return TaskfileError::InvalidSetting(ErrWithMessage {
+        code: "BUILD_ERROR".to_string(),
+        messages: vec![message],
+    });
+}
+
+impl Bundle {
+    // the Taskfile at `path` as a single document, validated the same way tasker loads it
+    pub fn new(name: &str, path: &Path) -> Result<Bundle, TaskfileError> {
+        let taskfile = Taskfile::new(path.to_string_lossy().to_string())?;
+        if !taskfile.includes.is_empty() {
+            return Err(bundle_error(
+                "`includes` point at files that do not ship with the CLI, convert them with `tasker import` first"
+                    .to_string(),
+            ));
+        }
+        let mut document: serde_yaml::Value = serde_yaml::from_reader(File::open(path)?)?;
+        // context files are loaded into `contexts`, so they can be written back the same way
+        let mut contexts = serde_yaml::Mapping::new();
+        for (name, context) in &taskfile.contexts {
+            if let Some(source) = &context.source {
+                let value: serde_yaml::Value = serde_yaml::from_reader(File::open(source)?)?;
+                contexts.insert(name.to_owned().into(), value);
+            }
+        }
+        if !contexts.is_empty() {
+            let mapping = document
+                .as_mapping_mut()
+                .ok_or_else(|| bundle_error("the Taskfile is not a mapping".to_string()))?;
+            let merged = mapping
+                .entry("contexts".into())
+                .or_insert_with(|| serde_yaml::Mapping::new().into());
+            if let Some(merged) = merged.as_mapping_mut() {
+                merged.extend(contexts);
+            }
+        }
+        return Ok(Bundle {
+            name: name.to_string(),
+            taskfile: serde_yaml::to_string(&document)?,
+        });
+    }
+    // the bundle a binary carries, if it was built with `tasker build-cli`, and where it starts
+    fn read(file: &mut File) -> Option<(Bundle, u64)> {
+        let size = file.metadata().ok()?.len();
+        if size < TRAILER {
+            return None;
+        }
+        let mut trailer = [0u8; TRAILER as usize];
+        file.seek(SeekFrom::End(-(TRAILER as i64))).ok()?;
+        file.read_exact(&mut trailer).ok()?;
+        if &trailer[8..] != MAGIC {
+            return None;
+        }
+        let length = u64::from_le_bytes(trailer[..8].try_into().ok()?);
+        let start = size.checked_sub(TRAILER + length)?;
+        let mut payload = vec![0u8; length as usize];
+        file.seek(SeekFrom::Start(start)).ok()?;
+        file.read_exact(&mut payload).ok()?;
+        let bundle = serde_json::from_slice(&payload).ok()?;
+        return Some((bundle, start));
+    }
+    // the bundle of the running binary
+    pub fn embedded() -> Option<Bundle> {
+        let mut file = File::open(std::env::current_exe().ok()?).ok()?;
+        return Self::read(&mut file).map(|(bundle, _)| bundle);
+    }
+    // `binary` without a bundle it may already carry, with this one appended
+    pub fn attach(&self, binary: &Path) -> Result<Vec<u8>, TaskfileError> {
+        let mut file = File::open(binary)?;
+        let end = match Self::read(&mut file) {
+            Some((_, start)) => start,
+            None => file.metadata()?.len(),
+        };
+        let mut output = vec![];
+        file.seek(SeekFrom::Start(0))?;
+        file.take(end).read_to_end(&mut output)?;
+        let payload = serde_json::to_vec(self).map_err(|e| bundle_error(e.to_string()))?;
+        output.extend(&payload);
+        output.extend((payload.len() as u64).to_le_bytes());
+        output.extend(MAGIC);
+        return Ok(output);
+    }
+}
+
+impl Taskfile {
+    // a Taskfile carried by a CLI built with `tasker build-cli`, providers look at the working
+    // directory since the project the CLI was built from is not around
+    pub fn from_bundle(bundle: &Bundle) -> Result<Taskfile, TaskfileError> {
+        let mut taskfile: Taskfile = serde_yaml::from_str(&bundle.taskfile)?;
+        let path = std::env::current_exe()?;
+        taskfile.path = Some(path.to_owned());
+        for task in taskfile.tasks.iter_mut() {
+            task.source = Some(path.to_owned());
+        }
+        taskfile.cli_name = Some(bundle.name.to_owned());
+        taskfile.load_provided_tasks(Path::new("."))?;
+        taskfile.validate_contexts()?;
+        taskfile.validate_shells()?;
+        return Ok(taskfile);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Bundle;
+    use crate::taskfile::Taskfile;
+    use std::fs::File;
+
+    #[test]
+    fn test_bundle() {
+        let dir = std::env::temp_dir().join(format!("tasker-embed-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("contexts")).unwrap();
+        std::fs::write(
+            dir.join("Taskfile"),
+            "project: deploy tools\ntasks:\n  - name: hello\n    args: []\n    commands:\n      - shell: echo ${region}\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("contexts/prod.yaml"), "region: eu-west\n").unwrap();
+        let bundle = Bundle::new("deployctl", &dir.join("Taskfile")).unwrap();
+        std::fs::write(dir.join("tasker"), b"\x7fELF not really").unwrap();
+        let binary = bundle.attach(&dir.join("tasker")).unwrap();
+        std::fs::write(dir.join("deployctl"), &binary).unwrap();
+        // building from a built CLI replaces its bundle instead of stacking another one
+        let rebuilt = bundle.attach(&dir.join("deployctl")).unwrap();
+        let (read, start) = Bundle::read(&mut File::open(dir.join("deployctl")).unwrap()).unwrap();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert_eq!(read, bundle);
+        assert_eq!(&binary[..start as usize], b"\x7fELF not really");
+        assert_eq!(rebuilt, binary);
+        let taskfile = Taskfile::from_bundle(&bundle).unwrap();
+        assert_eq!(taskfile.cli_name(), "deployctl");
+        assert_eq!(taskfile.project.as_deref(), Some("deploy tools"));
+        assert_eq!(
+            taskfile.get_context(&["prod".to_string()]).get("region"),
+            Some(&"eu-west".to_string())
+        );
+    }
+}
@@ -5,6 +5,7 @@ use crate::utils::errors::ErrWithMessage;
 pub enum TaskfileError {
     FileNotFound(ErrWithMessage),
     FileParseError(ErrWithMessage),
+    InvalidSetting(ErrWithMessage),
 }
 impl Error for TaskfileError {}
 impl fmt::Display for TaskfileError {
@@ -12,6 +13,7 @@ impl fmt::Display for TaskfileError {
         match self {
             TaskfileError::FileNotFound(e) => write!(f, "Taskfile does not exist:\n    {}", e),
             TaskfileError::FileParseError(e) => write!(f, "Taskfile Parsing Error:\n    {}", e),
+            TaskfileError::InvalidSetting(e) => write!(f, "Taskfile Setting Error:\n    {}", e),
         }
     }
 }
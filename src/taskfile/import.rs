@@ -0,0 +1,584 @@
+use super::{
+    cmd::{CmdArg, TaskCmd},
+    taskstanza::TaskStanza,
+};
+use serde::Deserialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+// the tools whose task files tasker can read
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    Make,
+    Just,
+    Npm,
+}
+impl ImportFormat {
+    // the file the tool reads when none is given
+    pub fn default_file(&self) -> &'static str {
+        return match self {
+            ImportFormat::Make => "Makefile",
+            ImportFormat::Just => "justfile",
+            ImportFormat::Npm => "package.json",
+        };
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedCommand {
+    pub command: String,
+    pub ignore_error: bool,
+}
+
+// a target, recipe or script, with its commands rewritten to tasker's `${var}` syntax
+#[derive(Debug, Clone, PartialEq)]
+pub struct ImportedTask {
+    pub name: String,
+    pub description: Option<String>,
+    // run first, in order
+    pub deps: Vec<String>,
+    pub commands: Vec<ImportedCommand>,
+    // run after the commands, npm `post` scripts
+    pub after: Vec<String>,
+    // recipe parameters, name and default, None when it has to be given
+    pub args: Vec<(String, Option<String>)>,
+    // variables of the file the commands use, with their values as defaults
+    pub variables: Vec<(String, Option<String>)>,
+}
+
+fn is_identifier(name: &str) -> bool {
+    return !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+}
+
+// `# text` on the line above a target or recipe
+fn comment(lines: &[String], i: usize) -> Option<String> {
+    let line = lines.get(i.checked_sub(1)?)?;
+    let text = line
+        .trim()
+        .strip_prefix('#')?
+        .trim_start_matches('#')
+        .trim();
+    return match text.is_empty() {
+        true => None,
+        false => Some(text.to_string()),
+    };
+}
+
+// `@` silences a line and `-` ignores its failure in both make and just
+fn recipe_line(line: &str) -> ImportedCommand {
+    let mut line = line.trim();
+    let mut ignore_error = false;
+    while let Some(rest) = line.strip_prefix(['@', '-']) {
+        ignore_error = ignore_error || line.starts_with('-');
+        line = rest.trim_start();
+    }
+    return ImportedCommand {
+        command: line.to_string(),
+        ignore_error,
+    };
+}
+
+// make reads `$(X)` and `${X}` as variables and `$$` as a literal `$`
+fn make_command(line: &str, target: &str, deps: &[String]) -> String {
+    let mut output = String::new();
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '$' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('$') => output.push('$'),
+            Some('@') => output.push_str(target),
+            Some('<') => output.push_str(deps.first().map(|dep| dep.as_str()).unwrap_or("")),
+            Some('^') => output.push_str(&deps.join(" ")),
+            Some(open @ ('(' | '{')) => {
+                let close = if open == '(' { ')' } else { '}' };
+                let mut depth = 1;
+                let mut inner = String::new();
+                for c in chars.by_ref() {
+                    depth += (c == open) as i32 - (c == close) as i32;
+                    if depth == 0 {
+                        break;
+                    }
+                    inner.push(c);
+                }
+                match inner.split_once(' ') {
+                    _ if inner == "MAKE" => output.push_str("make"),
+                    _ if is_identifier(&inner) => output.push_str(&format!("${{{}}}", inner)),
+                    Some(("shell", command)) => output.push_str(&format!("$({})", command)),
+                    // other make functions have no shell counterpart, keep them for a person to fix
+                    _ => output.push_str(&format!("${}{}{}", open, inner, close)),
+                }
+            }
+            Some(other) => {
+                output.push('$');
+                output.push(other);
+            }
+            None => output.push('$'),
+        }
+    }
+    return output;
+}
+
+// variables referenced by a command, in order of first use
+fn referenced(
+    commands: &[ImportedCommand],
+    variables: &BTreeMap<String, String>,
+) -> Vec<(String, Option<String>)> {
+    let mut args: Vec<(String, Option<String>)> = vec![];
+    for command in commands {
+        for part in command.command.split("${").skip(1) {
+            let name = match part.split_once('}') {
+                Some((name, _)) => name,
+                None => continue,
+            };
+            if let Some(default) = variables.get(name) {
+                if !args.iter().any(|(arg, _)| arg == name) {
+                    args.push((name.to_string(), Some(default.to_owned())));
+                }
+            }
+        }
+    }
+    return args;
+}
+
+// lines ending in `\` continue on the next one
+fn join_continued(text: &str) -> Vec<String> {
+    let mut lines: Vec<String> = vec![];
+    let mut continued = false;
+    for line in text.lines() {
+        match (continued, lines.last_mut()) {
+            (true, Some(last)) => {
+                last.pop();
+                last.truncate(last.trim_end().len());
+                last.push(' ');
+                last.push_str(line.trim_start());
+            }
+            _ => lines.push(line.to_string()),
+        }
+        continued = line.ends_with('\\');
+    }
+    return lines;
+}
+
+pub fn parse_makefile(text: &str) -> Vec<ImportedTask> {
+    let lines = join_continued(text);
+    let mut variables = BTreeMap::new();
+    let mut tasks: Vec<ImportedTask> = vec![];
+    // the targets of the last rule, `a b: c` gives both the recipe
+    let mut rule = 0;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with('\t') {
+            let command = recipe_line(line);
+            for task in tasks[rule..].iter_mut() {
+                task.commands.push(ImportedCommand {
+                    command: make_command(&command.command, &task.name, &task.deps),
+                    ignore_error: command.ignore_error,
+                });
+            }
+            continue;
+        }
+        if line.starts_with(['#', ' ']) || line.trim().is_empty() {
+            continue;
+        }
+        // special targets like `.PHONY`, their recipes are nobody's
+        if line.starts_with('.') {
+            rule = tasks.len();
+            continue;
+        }
+        // `X = 1`, `X := 1`, `X ?= 1` and `X += 1`
+        if let Some((name, value)) = line.split_once('=') {
+            let name = name.trim_end_matches([':', '?', '+', '!']).trim();
+            if is_identifier(name) {
+                let value = value.trim().to_string();
+                variables.insert(name.to_string(), make_command(&value, "", &[]));
+                continue;
+            }
+        }
+        let (names, rest) = match line.split_once(':') {
+            Some(split) => split,
+            None => continue,
+        };
+        let (deps, inline) = match rest.split_once("##") {
+            Some((deps, text)) => (deps, Some(text.trim().to_string())),
+            None => (rest, None),
+        };
+        let deps: Vec<String> = deps.split_whitespace().map(String::from).collect();
+        let description = inline.or_else(|| comment(&lines, i));
+        rule = tasks.len();
+        for name in names.split_whitespace() {
+            if name.contains(['%', '$']) {
+                continue;
+            }
+            tasks.push(ImportedTask {
+                name: name.to_string(),
+                description: description.to_owned(),
+                deps: deps.to_owned(),
+                commands: vec![],
+                after: vec![],
+                args: vec![],
+                variables: vec![],
+            });
+        }
+    }
+    // prerequisites that are files rather than targets are left to make
+    let names: Vec<String> = tasks.iter().map(|task| task.name.to_owned()).collect();
+    for task in tasks.iter_mut() {
+        task.deps.retain(|dep| names.contains(dep));
+        task.variables = referenced(&task.commands, &variables);
+    }
+    return tasks;
+}
+
+// `{{ x }}` is a just variable or parameter
+fn just_command(line: &str) -> String {
+    let mut output = String::new();
+    let mut rest = line;
+    while let Some(start) = rest.find("{{") {
+        output.push_str(&rest[..start]);
+        let end = match rest[start..].find("}}") {
+            Some(end) => start + end,
+            None => break,
+        };
+        let inner = rest[start + 2..end].trim();
+        match is_identifier(inner) {
+            true => output.push_str(&format!("${{{}}}", inner)),
+            false => output.push_str(&rest[start..end + 2]),
+        }
+        rest = &rest[end + 2..];
+    }
+    output.push_str(rest);
+    return output;
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = value
+            .strip_prefix(quote)
+            .and_then(|v| v.strip_suffix(quote))
+        {
+            return inner.to_string();
+        }
+    }
+    return value.to_string();
+}
+
+pub fn parse_justfile(text: &str) -> Vec<ImportedTask> {
+    let lines = join_continued(text);
+    let mut variables = BTreeMap::new();
+    let mut tasks: Vec<ImportedTask> = vec![];
+    let mut in_recipe = false;
+    for (i, line) in lines.iter().enumerate() {
+        if line.starts_with([' ', '\t']) {
+            if let (true, Some(task)) = (in_recipe, tasks.last_mut()) {
+                let command = recipe_line(line);
+                if !command.command.is_empty() {
+                    task.commands.push(ImportedCommand {
+                        command: just_command(&command.command),
+                        ignore_error: command.ignore_error,
+                    });
+                }
+            }
+            continue;
+        }
+        in_recipe = false;
+        let trimmed = line.trim();
+        if trimmed.is_empty()
+            || trimmed.starts_with(['#', '['])
+            || trimmed.starts_with("set ")
+            || trimmed.starts_with("alias ")
+        {
+            continue;
+        }
+        if let Some((name, value)) = trimmed.split_once(":=") {
+            let name = name.trim().trim_start_matches("export ").trim();
+            if is_identifier(name) {
+                variables.insert(name.to_string(), unquote(value));
+                continue;
+            }
+        }
+        // `name param="default" +rest: dep (dep "arg")`
+        let (head, deps) = match trimmed.split_once(':') {
+            Some(split) => split,
+            None => continue,
+        };
+        let mut head = head.split_whitespace();
+        let name = head.next().unwrap_or_default().trim_start_matches('@');
+        let args = head
+            .map(|param| {
+                let param = param.trim_start_matches(['+', '*', '$']);
+                match param.split_once('=') {
+                    Some((name, default)) => (name.to_string(), Some(unquote(default))),
+                    None => (param.to_string(), None),
+                }
+            })
+            .filter(|(name, _)| is_identifier(name))
+            .collect();
+        // `(dep "arg")` calls a dependency with arguments, those are left out
+        let mut dep_names = vec![];
+        let mut in_call = false;
+        for dep in deps.split_whitespace() {
+            if !in_call {
+                dep_names.push(dep.trim_matches(['(', ')']).to_string());
+            }
+            in_call = (dep.starts_with('(') || in_call) && !dep.ends_with(')');
+        }
+        tasks.push(ImportedTask {
+            name: name.to_string(),
+            description: comment(&lines, i),
+            deps: dep_names,
+            commands: vec![],
+            after: vec![],
+            args,
+            variables: vec![],
+        });
+        in_recipe = true;
+    }
+    // parameters shadow variables of the same name
+    for task in tasks.iter_mut() {
+        let params: Vec<String> = task.args.iter().map(|(name, _)| name.to_owned()).collect();
+        task.variables = referenced(&task.commands, &variables)
+            .into_iter()
+            .filter(|(name, _)| !params.contains(name))
+            .collect();
+    }
+    return tasks;
+}
+
+// `pre<x>` and `post<x>` scripts run around `<x>` the way npm runs them
+pub fn parse_package_json(text: &str) -> Result<Vec<ImportedTask>, String> {
+    let manifest: serde_json::Value = serde_json::from_str(text).map_err(|e| e.to_string())?;
+    let scripts = match manifest
+        .get("scripts")
+        .and_then(|scripts| scripts.as_object())
+    {
+        Some(scripts) => scripts,
+        None => return Ok(vec![]),
+    };
+    let mut tasks = vec![];
+    for (name, script) in scripts {
+        let script = match script.as_str() {
+            Some(script) => script,
+            None => continue,
+        };
+        let hook = |prefix: &str| {
+            let hook = format!("{}{}", prefix, name);
+            return match scripts.contains_key(&hook) {
+                true => vec![hook],
+                false => vec![],
+            };
+        };
+        tasks.push(ImportedTask {
+            name: name.to_owned(),
+            description: Some(format!("npm script `{}`", name)),
+            deps: hook("pre"),
+            commands: vec![ImportedCommand {
+                command: script.to_string(),
+                ignore_error: false,
+            }],
+            after: hook("post"),
+            args: vec![],
+            variables: vec![],
+        });
+    }
+    return Ok(tasks);
+}
+
+pub fn parse(format: ImportFormat, text: &str) -> Result<Vec<ImportedTask>, String> {
+    return match format {
+        ImportFormat::Make => Ok(parse_makefile(text)),
+        ImportFormat::Just => Ok(parse_justfile(text)),
+        ImportFormat::Npm => parse_package_json(text),
+    };
+}
+
+pub fn parse_file(format: ImportFormat, path: &Path) -> Result<Vec<ImportedTask>, String> {
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| format!("could not read `{}`: {}", path.display(), e))?;
+    return parse(format, &text)
+        .map_err(|e| format!("could not parse `{}`: {}", path.display(), e));
+}
+
+fn shell_quote(value: &str) -> String {
+    let plain = !value.is_empty()
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_./:@+,=".contains(c));
+    return match plain {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', "'\\''")),
+    };
+}
+
+// an `includes:` entry, its targets run through the tool itself instead of being converted
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct Include {
+    pub from: ImportFormat,
+    // relative to the Taskfile, the tool's usual file name when left out
+    #[serde(default)]
+    pub path: Option<PathBuf>,
+    // put in front of every task name, `make` gives `make:build`
+    #[serde(default)]
+    pub namespace: Option<String>,
+}
+impl Include {
+    // the command handing the task to the tool, args and variables are passed on so tasker's
+    // defaults, contexts and env vars apply
+    fn delegate(&self, path: &Path, task: &ImportedTask) -> String {
+        let dir = path.parent().unwrap_or(Path::new(""));
+        let file = path.file_name().unwrap_or_default().to_string_lossy();
+        let in_dir = !dir.as_os_str().is_empty() && dir != Path::new(".");
+        let mut command = match self.from {
+            ImportFormat::Make => {
+                let mut command = String::from("make");
+                if in_dir {
+                    command.push_str(&format!(
+                        " --no-print-directory -C {}",
+                        shell_quote(&dir.to_string_lossy())
+                    ));
+                }
+                if file != "Makefile" {
+                    command.push_str(&format!(" -f {}", shell_quote(&file)));
+                }
+                command.push_str(&format!(" {}", shell_quote(&task.name)));
+                for (name, _) in &task.variables {
+                    command.push_str(&format!(" {0}=\"${{{0}}}\"", name));
+                }
+                command
+            }
+            ImportFormat::Just => {
+                let mut command = format!("just -f {}", shell_quote(&path.to_string_lossy()));
+                for (name, _) in &task.variables {
+                    command.push_str(&format!(" --set {0} \"${{{0}}}\"", name));
+                }
+                command.push_str(&format!(" {}", shell_quote(&task.name)));
+                command
+            }
+            ImportFormat::Npm => {
+                let mut command = String::from("npm run");
+                if in_dir {
+                    command.push_str(&format!(
+                        " --prefix {}",
+                        shell_quote(&dir.to_string_lossy())
+                    ));
+                }
+                command.push_str(&format!(" {}", shell_quote(&task.name)));
+                command
+            }
+        };
+        for (name, _) in &task.args {
+            command.push_str(&format!(" \"${{{}}}\"", name));
+        }
+        return command;
+    }
+    pub(super) fn tasks(&self, dir: &Path) -> Result<Vec<TaskStanza>, String> {
+        let file = match &self.path {
+            Some(path) => path.to_owned(),
+            None => PathBuf::from(self.from.default_file()),
+        };
+        let path = dir.join(file);
+        let tasks = parse_file(self.from, &path)?
+            .iter()
+            .map(|task| {
+                let name = match &self.namespace {
+                    Some(namespace) => format!("{}:{}", namespace, task.name),
+                    None => task.name.to_owned(),
+                };
+                let description = match &task.description {
+                    Some(description) => description.to_owned(),
+                    None => format!("`{}` from {}", task.name, path.display()),
+                };
+                let args = task
+                    .args
+                    .iter()
+                    .chain(task.variables.iter())
+                    .map(|(name, default)| CmdArg::string(name, default.to_owned()))
+                    .collect();
+                let command = TaskCmd::shell(&self.delegate(&path, task));
+                TaskStanza::generated(
+                    name,
+                    description,
+                    vec![command],
+                    args,
+                    "included",
+                    path.to_owned(),
+                )
+            })
+            .collect();
+        return Ok(tasks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_justfile, parse_makefile, parse_package_json};
+
+    #[test]
+    fn test_parse_makefile() {
+        let makefile = "CC ?= gcc\nCFLAGS := -O2\n\n.PHONY: build test\n# build the binary\nbuild: main.o deps\n\t@$(CC) $(CFLAGS) -o $@ $^ \\\n\t  -lm\n\t-rm -f *.tmp\n\ndeps:\n\techo $$HOME $(shell date)\n\ntest: build ## run the tests\n\t$(MAKE) check\n";
+        let tasks = parse_makefile(makefile);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "deps", "test"]);
+        assert_eq!(tasks[0].description.as_deref(), Some("build the binary"));
+        assert_eq!(tasks[0].deps, vec!["deps"]);
+        assert_eq!(
+            tasks[0].commands[0].command,
+            "${CC} ${CFLAGS} -o build main.o deps -lm"
+        );
+        assert!(tasks[0].commands[1].ignore_error);
+        assert_eq!(
+            tasks[0].variables,
+            vec![
+                ("CC".to_string(), Some("gcc".to_string())),
+                ("CFLAGS".to_string(), Some("-O2".to_string()))
+            ]
+        );
+        assert_eq!(tasks[1].commands[0].command, "echo $HOME $(date)");
+        assert_eq!(tasks[2].description.as_deref(), Some("run the tests"));
+        assert_eq!(tasks[2].commands[0].command, "make check");
+    }
+    #[test]
+    fn test_parse_justfile() {
+        let justfile = "set shell := [\"bash\", \"-c\"]\nimage := \"app\"\n\n# build the image\nbuild tag=\"latest\": lint\n    docker build -t {{image}}:{{tag}} .\n\n[private]\nlint:\n    -cargo clippy\n\ndeploy env +flags: (build \"prod\")\n    ./deploy.sh {{ env }} {{flags}}\n";
+        let tasks = parse_justfile(justfile);
+        let names: Vec<&str> = tasks.iter().map(|task| task.name.as_str()).collect();
+        assert_eq!(names, vec!["build", "lint", "deploy"]);
+        assert_eq!(tasks[0].description.as_deref(), Some("build the image"));
+        assert_eq!(tasks[0].deps, vec!["lint"]);
+        assert_eq!(
+            tasks[0].commands[0].command,
+            "docker build -t ${image}:${tag} ."
+        );
+        assert_eq!(
+            tasks[0].args,
+            vec![("tag".to_string(), Some("latest".to_string()))]
+        );
+        assert_eq!(
+            tasks[0].variables,
+            vec![("image".to_string(), Some("app".to_string()))]
+        );
+        assert!(tasks[1].commands[0].ignore_error);
+        assert_eq!(tasks[2].deps, vec!["build"]);
+        assert_eq!(
+            tasks[2].args,
+            vec![("env".to_string(), None), ("flags".to_string(), None)]
+        );
+    }
+    #[test]
+    fn test_parse_package_json() {
+        let tasks = parse_package_json(
+            r#"{"scripts": {"prebuild": "rm -rf dist", "build": "tsc", "postbuild": "echo done"}}"#,
+        )
+        .unwrap();
+        assert_eq!(tasks[0].name, "build");
+        assert_eq!(tasks[0].deps, vec!["prebuild"]);
+        assert_eq!(tasks[0].after, vec!["postbuild"]);
+        assert!(parse_package_json("{").is_err());
+    }
+}
@@ -0,0 +1,162 @@
+use super::taskfile::Taskfile;
+use std::{fmt, path::PathBuf};
+
+// where in the Taskfile (or a context file) a value was written
+#[derive(Clone, Debug, PartialEq)]
+pub struct Location {
+    pub file: PathBuf,
+    // unknown when the YAML is written in a way the line search does not follow, e.g. `{a: b}`
+    pub line: Option<usize>,
+}
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.line {
+            Some(line) => write!(f, "{}:{}", self.file.display(), line),
+            None => write!(f, "{}", self.file.display()),
+        }
+    }
+}
+
+fn unquote(text: &str) -> &str {
+    let text = text.trim();
+    for quote in ['"', '\''] {
+        if let Some(inner) = text
+            .strip_prefix(quote)
+            .and_then(|text| text.strip_suffix(quote))
+        {
+            return inner;
+        }
+    }
+    return text;
+}
+
+// 1 based line of the key reached by following `path` down block style YAML, each step is a
+// key and optionally the value it needs to have, to pick an item out of a `- name: x` list
+fn find_line(text: &str, path: &[(&str, Option<&str>)]) -> Option<usize> {
+    let mut step = 0;
+    // indentation of the last match and whether it started a list item
+    let mut scope: Option<(usize, bool)> = None;
+    for (number, line) in text.lines().enumerate() {
+        let trimmed = line.trim_start();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        let indent = line.len() - trimmed.len();
+        let (dashed, entry) = match trimmed.strip_prefix("- ") {
+            Some(entry) => (true, entry),
+            None => (false, trimmed),
+        };
+        if let Some((scope_indent, scope_dashed)) = scope {
+            // list items may sit at the same indentation as the key holding them
+            let inside =
+                indent > scope_indent || (!scope_dashed && indent == scope_indent && dashed);
+            if !inside {
+                return None;
+            }
+        }
+        let (key, value) = match entry.split_once(':') {
+            Some(pair) => pair,
+            None => continue,
+        };
+        let (wanted_key, wanted_value) = path[step];
+        if unquote(key) != wanted_key {
+            continue;
+        }
+        if wanted_value.is_some_and(|wanted| unquote(value) != wanted) {
+            continue;
+        }
+        step += 1;
+        if step == path.len() {
+            return Some(number + 1);
+        }
+        scope = Some((indent, dashed));
+    }
+    return None;
+}
+
+fn locate(file: Option<&PathBuf>, path: &[(&str, Option<&str>)]) -> Option<Location> {
+    let file = file?;
+    let line = std::fs::read_to_string(file)
+        .ok()
+        .and_then(|text| find_line(&text, path));
+    return Some(Location {
+        file: file.to_owned(),
+        line,
+    });
+}
+
+impl Taskfile {
+    // where the default of a task's arg is written
+    pub fn locate_arg(&self, task_name: &str, arg_name: &str) -> Option<Location> {
+        let task = self.get_task_by_name(task_name)?;
+        let path = [
+            ("tasks", None),
+            ("name", Some(task_name)),
+            ("args", None),
+            ("name", Some(arg_name)),
+        ];
+        return locate(task.source.as_ref(), &path);
+    }
+    pub fn locate_context_value(&self, context_name: &str, key: &str) -> Option<Location> {
+        let context = self.contexts.get(context_name)?;
+        return match &context.source {
+            Some(file) => locate(Some(file), &[(key, None)]),
+            None => locate(
+                self.path.as_ref(),
+                &[("contexts", None), (context_name, None), (key, None)],
+            ),
+        };
+    }
+    pub fn locate_secret(&self, name: &str) -> Option<Location> {
+        return locate(self.path.as_ref(), &[("secrets", None), (name, None)]);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::find_line;
+
+    const TASKFILE: &str = r#"contexts:
+  prod:
+    extends: [base]
+    region: "eu-west-1"
+tasks:
+  - name: build
+    args:
+      - name: region
+        default: us-east-1
+  - name: deploy
+    # the region to deploy to
+    args:
+    - name: "region"
+      type: string
+"#;
+
+    #[test]
+    fn test_find_line() {
+        let path = [("contexts", None), ("prod", None), ("region", None)];
+        assert_eq!(find_line(TASKFILE, &path), Some(4));
+        let path = [
+            ("tasks", None),
+            ("name", Some("deploy")),
+            ("args", None),
+            ("name", Some("region")),
+        ];
+        assert_eq!(find_line(TASKFILE, &path), Some(13));
+        let path = [
+            ("tasks", None),
+            ("name", Some("build")),
+            ("args", None),
+            ("name", Some("region")),
+        ];
+        assert_eq!(find_line(TASKFILE, &path), Some(8));
+    }
+    #[test]
+    fn test_find_line_stays_in_scope() {
+        // `build` has no `type` key, the one of `deploy` must not be picked up
+        let path = [("tasks", None), ("name", Some("build")), ("type", None)];
+        assert_eq!(find_line(TASKFILE, &path), None);
+        let path = [("contexts", None), ("prod", None), ("tasks", None)];
+        assert_eq!(find_line(TASKFILE, &path), None);
+    }
+}
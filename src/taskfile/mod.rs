@@ -1,9 +1,22 @@
 mod cmd;
+mod context;
+mod detect;
+mod embed;
 mod errors;
+mod import;
+mod locate;
+mod secrets;
+mod settings;
 mod taskfile;
 mod taskstanza;
 
-pub use cmd::{CmdArg, CommandTypes};
+pub use cmd::{CmdArg, CommandTypes, TaskCmd};
+pub use detect::{detect, Detected};
+pub use embed::Bundle;
 pub use errors::TaskfileError;
+pub use import::{parse_file, ImportFormat, ImportedTask};
+pub use locate::Location;
+pub use secrets::SecretSource;
+pub use settings::{Backoff, RetryPolicy, RunMode, Shell};
 pub use taskfile::Taskfile;
 pub use taskstanza::TaskStanza;
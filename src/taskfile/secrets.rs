@@ -0,0 +1,86 @@
+use serde::Deserialize;
+use std::{env, fmt, fs};
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSecretSource {
+    env: Option<String>,
+    file: Option<String>,
+}
+
+// where the value of a `secrets:` entry is read from, written as `{env: NAME}` or `{file: path}`
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(try_from = "RawSecretSource")]
+pub enum SecretSource {
+    Env(String),
+    File(String),
+}
+impl TryFrom<RawSecretSource> for SecretSource {
+    type Error = String;
+    fn try_from(raw: RawSecretSource) -> Result<Self, Self::Error> {
+        match (raw.env, raw.file) {
+            (Some(name), None) => Ok(SecretSource::Env(name)),
+            (None, Some(path)) => Ok(SecretSource::File(path)),
+            _ => Err("a secret needs exactly one of `env` or `file`".to_string()),
+        }
+    }
+}
+impl fmt::Display for SecretSource {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SecretSource::Env(name) => write!(f, "env `{}`", name),
+            SecretSource::File(path) => write!(f, "file `{}`", path),
+        }
+    }
+}
+impl SecretSource {
+    pub fn read(&self) -> Result<String, String> {
+        match self {
+            SecretSource::Env(name) => {
+                env::var(name).map_err(|_| format!("environment variable `{}` is not set", name))
+            }
+            SecretSource::File(path) => {
+                let path = shellexpand::tilde(path).to_string();
+                match fs::read_to_string(&path) {
+                    // files usually end in a newline nobody means to be part of the secret
+                    Ok(value) => Ok(value.trim_end_matches(['\n', '\r']).to_string()),
+                    Err(e) => Err(format!("could not read `{}`: {}", path, e)),
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SecretSource;
+    use std::collections::BTreeMap;
+
+    #[test]
+    fn test_deserialize_secret_sources() {
+        let yaml = "api_token: {env: TASKER_TEST_TOKEN}\ndb_password: {file: ~/.db}";
+        let secrets: BTreeMap<String, SecretSource> = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            secrets["api_token"],
+            SecretSource::Env("TASKER_TEST_TOKEN".to_string())
+        );
+        assert_eq!(
+            secrets["db_password"],
+            SecretSource::File("~/.db".to_string())
+        );
+        let both: Result<SecretSource, _> = serde_yaml::from_str("{env: A, file: b}");
+        assert!(both.is_err());
+    }
+    #[test]
+    fn test_read_secret_sources() {
+        let path = std::env::temp_dir().join(format!("tasker-secret-{}", std::process::id()));
+        std::fs::write(&path, "hunter2\n").unwrap();
+        let source = SecretSource::File(path.display().to_string());
+        assert_eq!(source.read().unwrap(), "hunter2");
+        let source = SecretSource::Env("TASKER_TEST_SECRET_THAT_IS_NOT_SET".to_string());
+        assert_eq!(
+            source.read().unwrap_err(),
+            "environment variable `TASKER_TEST_SECRET_THAT_IS_NOT_SET` is not set"
+        );
+    }
+}
@@ -0,0 +1,226 @@
+use crate::utils::strings::{parse_duration, split_exclude_quotes};
+use serde::{Deserialize, Deserializer};
+use std::{fmt, path::Path, time::Duration};
+
+// how the commands of a task are handed to the shell
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RunMode {
+    // every command runs as its own process so failures can be caught per command
+    Steps,
+    // commands are joined into a single script and share shell state (cd, exports, etc)
+    #[default]
+    Script,
+}
+
+// the program commands are handed to, written like `bash -euo pipefail -c`
+#[derive(Clone, Debug, PartialEq)]
+pub struct Shell {
+    pub program: String,
+    // the command is appended after these
+    pub args: Vec<String>,
+}
+impl Default for Shell {
+    fn default() -> Self {
+        Shell {
+            program: "sh".to_string(),
+            args: vec!["-c".to_string()],
+        }
+    }
+}
+impl Shell {
+    // strict mode and step markers rely on `set` and `printf`, which only the sh family has
+    pub fn is_posix(&self) -> bool {
+        let name = Path::new(&self.program)
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        matches!(
+            name,
+            "sh" | "bash" | "dash" | "zsh" | "ksh" | "mksh" | "ash"
+        )
+    }
+}
+impl fmt::Display for Shell {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.program)?;
+        for arg in &self.args {
+            write!(f, " {}", arg)?;
+        }
+        Ok(())
+    }
+}
+impl<'de> Deserialize<'de> for Shell {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let raw = String::deserialize(deserializer)?;
+        let mut words = split_exclude_quotes(raw.trim().to_string())
+            .into_iter()
+            .filter(|word| !word.is_empty());
+        match words.next() {
+            Some(program) => Ok(Shell {
+                program,
+                args: words.collect(),
+            }),
+            None => Err(serde::de::Error::custom("shell can not be empty")),
+        }
+    }
+}
+
+// a duration written like `90s`, `5m` or `1h30m`
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HumanDuration(pub Duration);
+impl<'de> Deserialize<'de> for HumanDuration {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // bare numbers are seconds, so accept them as well as strings
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawDuration {
+            Seconds(u64),
+            Text(String),
+        }
+        let raw = match RawDuration::deserialize(deserializer)? {
+            RawDuration::Seconds(seconds) => {
+                return Ok(HumanDuration(Duration::from_secs(seconds)))
+            }
+            RawDuration::Text(text) => text,
+        };
+        match parse_duration(&raw) {
+            Ok(duration) => Ok(HumanDuration(duration)),
+            Err(e) => Err(serde::de::Error::custom(e)),
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Backoff {
+    #[default]
+    Fixed,
+    Exponential,
+}
+
+// retries can be a bare count or a map with the full policy
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRetryPolicy {
+    Count(u32),
+    Policy {
+        count: u32,
+        #[serde(default)]
+        backoff: Backoff,
+        #[serde(default)]
+        delay: Option<HumanDuration>,
+        #[serde(default)]
+        only_on: Vec<i32>,
+    },
+}
+
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(from = "RawRetryPolicy")]
+pub struct RetryPolicy {
+    pub count: u32,
+    pub backoff: Backoff,
+    pub delay: Duration,
+    // exit codes worth retrying, empty means any failure
+    pub only_on: Vec<i32>,
+}
+impl From<RawRetryPolicy> for RetryPolicy {
+    fn from(raw: RawRetryPolicy) -> Self {
+        let default_delay = Duration::from_secs(1);
+        match raw {
+            RawRetryPolicy::Count(count) => RetryPolicy {
+                count,
+                backoff: Backoff::Fixed,
+                delay: default_delay,
+                only_on: vec![],
+            },
+            RawRetryPolicy::Policy {
+                count,
+                backoff,
+                delay,
+                only_on,
+            } => RetryPolicy {
+                count,
+                backoff,
+                delay: delay.map(|d| d.0).unwrap_or(default_delay),
+                only_on,
+            },
+        }
+    }
+}
+impl RetryPolicy {
+    // how long to wait before retry number `attempt`, counting from 0
+    pub fn delay_for(&self, attempt: u32) -> Duration {
+        match self.backoff {
+            Backoff::Fixed => self.delay,
+            Backoff::Exponential => self.delay.saturating_mul(2u32.saturating_pow(attempt)),
+        }
+    }
+    pub fn retries_exit_code(&self, exit_code: i32) -> bool {
+        self.only_on.is_empty() || self.only_on.contains(&exit_code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Backoff, HumanDuration, RetryPolicy, RunMode, Shell};
+    use std::time::Duration;
+
+    #[test]
+    fn test_deserialize_run_mode() {
+        let mode: RunMode = serde_yaml::from_str("steps").unwrap();
+        assert_eq!(mode, RunMode::Steps);
+        assert_eq!(RunMode::default(), RunMode::Script);
+    }
+    #[test]
+    fn test_deserialize_retry_count() {
+        let policy: RetryPolicy = serde_yaml::from_str("3").unwrap();
+        assert_eq!(policy.count, 3);
+        assert_eq!(policy.backoff, Backoff::Fixed);
+        assert_eq!(policy.delay_for(2), Duration::from_secs(1));
+        assert!(policy.retries_exit_code(42));
+    }
+    #[test]
+    fn test_deserialize_retry_policy() {
+        let yaml = r#"
+        count: 4
+        backoff: exponential
+        delay: 500ms
+        only_on: [7]
+        "#;
+        let policy: RetryPolicy = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(policy.delay_for(0), Duration::from_millis(500));
+        assert_eq!(policy.delay_for(3), Duration::from_secs(4));
+        assert!(policy.retries_exit_code(7));
+        assert!(!policy.retries_exit_code(1));
+    }
+    #[test]
+    fn test_deserialize_human_duration() {
+        let timeout: HumanDuration = serde_yaml::from_str("5m").unwrap();
+        assert_eq!(timeout.0, Duration::from_secs(300));
+        let timeout: HumanDuration = serde_yaml::from_str("30").unwrap();
+        assert_eq!(timeout.0, Duration::from_secs(30));
+        let timeout: Result<HumanDuration, _> = serde_yaml::from_str("soon");
+        assert!(timeout.is_err());
+    }
+    #[test]
+    fn test_deserialize_shell() {
+        let shell: Shell = serde_yaml::from_str("bash  -euo pipefail -c").unwrap();
+        assert_eq!(shell.program, "bash");
+        assert_eq!(shell.args, vec!["-euo", "pipefail", "-c"]);
+        assert_eq!(shell.to_string(), "bash -euo pipefail -c");
+        assert_eq!(Shell::default().to_string(), "sh -c");
+        let shell: Result<Shell, _> = serde_yaml::from_str("' '");
+        assert!(shell.is_err());
+        let shell: Shell = serde_yaml::from_str("/usr/local/bin/bash -c").unwrap();
+        assert!(shell.is_posix());
+        let shell: Shell = serde_yaml::from_str("python3 -c").unwrap();
+        assert!(!shell.is_posix());
+    }
+}
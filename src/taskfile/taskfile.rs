@@ -1,40 +1,323 @@
 use serde::Deserialize;
-use std::collections::HashMap;
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::{Path, PathBuf},
+};
 
-use super::{errors::TaskfileError, taskstanza::TaskStanza};
-
-type TaskContext = HashMap<String, String>;
+use super::{
+    cmd::{CommandTypes, TaskCmd},
+    context::{defining_context, is_protected, resolve_context, secret_keys, TaskContext},
+    detect::{detect, provider_names},
+    errors::TaskfileError,
+    import::Include,
+    secrets::SecretSource,
+    settings::{RunMode, Shell},
+    taskstanza::TaskStanza,
+};
+use crate::utils::{errors::ErrWithMessage, paths::find_executable};
 
 // Taskfile File made from assembling above structs
 #[derive(Deserialize, Clone)]
 pub struct Taskfile {
+    // shown in the help and `--version` of the CLI
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub author: Option<String>,
+    // contexts from `contexts/*.yaml` next to the Taskfile are merged in on load
+    #[serde(default)]
     pub contexts: HashMap<String, TaskContext>,
+    // used when no context is picked with `-x` or TASKER_CONTEXT
+    #[serde(default)]
+    pub default_context: Option<String>,
+    // variables read from env vars or files, masked in everything tasker prints
+    #[serde(default)]
+    pub secrets: BTreeMap<String, SecretSource>,
     pub tasks: Vec<TaskStanza>,
+    // Makefiles, justfiles and package.json scripts exposed as tasks
+    #[serde(default)]
+    pub includes: Vec<Include>,
+    // tools whose project files next to the Taskfile contribute tasks, e.g. `cargo:test`
+    #[serde(default)]
+    pub providers: Vec<String>,
+    #[serde(default)]
+    pub mode: RunMode,
+    #[serde(default)]
+    pub shell: Shell,
+    // run commands with errexit, nounset and pipefail
+    #[serde(default)]
+    pub strict: bool,
+    // where the Taskfile was loaded from
+    #[serde(skip)]
+    pub path: Option<PathBuf>,
+    // the name of a CLI built with `tasker build-cli`
+    #[serde(skip)]
+    pub cli_name: Option<String>,
 }
 
 impl Taskfile {
     pub fn new(file_path: String) -> Result<Taskfile, TaskfileError> {
-        let file = std::fs::File::open(file_path)?;
-        let base_deserialized_config: Taskfile = serde_yaml::from_reader(file)?;
+        let file = std::fs::File::open(&file_path)?;
+        let mut base_deserialized_config: Taskfile = serde_yaml::from_reader(file)?;
+        base_deserialized_config.path = Some(PathBuf::from(&file_path));
+        for task in base_deserialized_config.tasks.iter_mut() {
+            task.source = Some(PathBuf::from(&file_path));
+        }
+        if let Some(dir) = Path::new(&file_path).parent() {
+            base_deserialized_config.load_context_files(&dir.join("contexts"))?;
+            base_deserialized_config.load_included_tasks(dir)?;
+            base_deserialized_config.load_provided_tasks(dir)?;
+        }
+        base_deserialized_config.validate_contexts()?;
+        base_deserialized_config.validate_shells()?;
         Ok(base_deserialized_config)
     }
+    // each `<name>.yaml` in the directory defines the context `<name>`
+    fn load_context_files(&mut self, dir: &Path) -> Result<(), TaskfileError> {
+        if !dir.is_dir() {
+            return Ok(());
+        }
+        let mut paths: Vec<_> = std::fs::read_dir(dir)?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| {
+                matches!(
+                    path.extension().and_then(|ext| ext.to_str()),
+                    Some("yaml") | Some("yml")
+                )
+            })
+            .collect();
+        paths.sort();
+        for path in paths {
+            let name = path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            if self.contexts.contains_key(&name) {
+                return Err(TaskfileError::InvalidSetting(ErrWithMessage {
+                    code: "DUPLICATE_CONTEXT".to_string(),
+                    messages: vec![format!(
+                        "context `{}` is defined in the Taskfile and in {}",
+                        name,
+                        path.display()
+                    )],
+                }));
+            }
+            let mut context: TaskContext = serde_yaml::from_reader(std::fs::File::open(&path)?)?;
+            context.source = Some(path.to_owned());
+            self.contexts.insert(name, context);
+        }
+        Ok(())
+    }
+    // tasks defined in the Taskfile win over included ones of the same name
+    fn load_included_tasks(&mut self, dir: &Path) -> Result<(), TaskfileError> {
+        let includes = self.includes.clone();
+        for include in includes {
+            let included = include.tasks(dir).map_err(|e| {
+                TaskfileError::InvalidSetting(ErrWithMessage {
+                    code: "INCLUDE_ERROR".to_string(),
+                    messages: vec![e],
+                })
+            })?;
+            for task in included {
+                if self.get_task_by_name(&task.name).is_none() {
+                    self.tasks.push(task);
+                }
+            }
+        }
+        Ok(())
+    }
+    // tasks defined in the Taskfile, or included, win over provided ones of the same name
+    pub(super) fn load_provided_tasks(&mut self, dir: &Path) -> Result<(), TaskfileError> {
+        let known = provider_names();
+        let unknown: Vec<_> = self
+            .providers
+            .iter()
+            .filter(|name| !known.contains(&name.as_str()))
+            .map(|name| {
+                format!(
+                    "provider `{}` does not exist, pick from {}",
+                    name,
+                    known.join(", ")
+                )
+            })
+            .collect();
+        if !unknown.is_empty() {
+            return Err(TaskfileError::InvalidSetting(ErrWithMessage {
+                code: "UNKNOWN_PROVIDER".to_string(),
+                messages: unknown,
+            }));
+        }
+        if self.providers.is_empty() {
+            return Ok(());
+        }
+        let detected = detect(dir)
+            .into_iter()
+            .filter(|detected| self.providers.iter().any(|name| name == detected.namespace));
+        for project in detected {
+            for task in &project.tasks {
+                let provided = TaskStanza::generated(
+                    format!("{}:{}", project.namespace, task.name),
+                    task.description.to_owned(),
+                    vec![TaskCmd::shell(&task.command)],
+                    vec![],
+                    "provided",
+                    dir.join(project.file),
+                );
+                if self.get_task_by_name(&provided.name).is_none() {
+                    self.tasks.push(provided);
+                }
+            }
+        }
+        Ok(())
+    }
+    // catch `extends` loops and typos on load
+    pub(super) fn validate_contexts(&self) -> Result<(), TaskfileError> {
+        for name in self.contexts.keys() {
+            resolve_context(&self.contexts, name)?;
+        }
+        let mut references = vec![];
+        if let Some(name) = &self.default_context {
+            references.push(("default_context".to_string(), name));
+        }
+        for task in &self.tasks {
+            for name in &task.contexts {
+                references.push((format!("task `{}`", task.name), name));
+            }
+        }
+        let messages: Vec<String> = references
+            .iter()
+            .filter(|(_, name)| !self.contexts.contains_key(*name))
+            .map(|(label, name)| {
+                format!("{} names context `{}`, which does not exist", label, name)
+            })
+            .collect();
+        if messages.is_empty() {
+            return Ok(());
+        }
+        Err(TaskfileError::InvalidSetting(ErrWithMessage {
+            code: "UNKNOWN_CONTEXT".to_string(),
+            messages,
+        }))
+    }
+    // keys of the given contexts whose values are secret
+    pub fn context_secret_keys(&self, names: &[String]) -> Vec<String> {
+        let mut keys: Vec<String> = names
+            .iter()
+            .flat_map(|name| secret_keys(&self.contexts, name))
+            .collect();
+        keys.sort();
+        keys.dedup();
+        return keys;
+    }
+    // the context that sets `key` for `name`, which is a parent when `name` inherits it
+    pub fn context_value_origin(&self, name: &str, key: &str) -> Option<String> {
+        return defining_context(&self.contexts, name, key);
+    }
+    pub fn is_context_protected(&self, name: &str) -> bool {
+        return is_protected(&self.contexts, name);
+    }
+    pub fn context_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.contexts.keys().map(|name| name.as_str()).collect();
+        names.sort();
+        return names;
+    }
+    // catch a missing shell on load instead of halfway through a task
+    pub(super) fn validate_shells(&self) -> Result<(), TaskfileError> {
+        let mut shells = vec![("Taskfile".to_string(), &self.shell)];
+        for task in &self.tasks {
+            let label = format!("task `{}`", task.name);
+            if let Some(shell) = &task.shell {
+                shells.push((label.to_owned(), shell));
+            }
+            for cmd in task.get_all_task_cmds() {
+                if let Some(shell) = &cmd.interpreter {
+                    shells.push((label.to_owned(), shell));
+                }
+            }
+        }
+        let messages: Vec<String> = shells
+            .iter()
+            .filter(|(_, shell)| find_executable(&shell.program).is_none())
+            .map(|(label, shell)| {
+                format!(
+                    "shell `{}` for {} was not found on PATH",
+                    shell.program, label
+                )
+            })
+            .collect();
+        if messages.is_empty() {
+            return Ok(());
+        }
+        Err(TaskfileError::InvalidSetting(ErrWithMessage {
+            code: "SHELL_NOT_FOUND".to_string(),
+            messages,
+        }))
+    }
     pub fn get_task_by_name(&self, name: &str) -> Option<&TaskStanza> {
         return self.tasks.iter().find(|&obj| obj.name == name);
     }
-    pub fn get_context(&self, value: Option<String>) -> HashMap<String, String> {
-        let default = HashMap::<String, String>::new();
-        return match value {
-            Some(context_name) => {
-                let task_context = self.contexts.get(&context_name);
-                match task_context {
-                    Some(context) => context.to_owned(),
-                    None => default,
+    // the task and every task its `task:` commands call, as far as the names are known
+    // before variables are filled in
+    pub fn reachable_tasks(&self, name: &str) -> Vec<&TaskStanza> {
+        let mut found: Vec<&TaskStanza> = vec![];
+        let mut pending = vec![name.to_string()];
+        while let Some(name) = pending.pop() {
+            let task = match self.get_task_by_name(&name) {
+                Some(task) if !found.iter().any(|seen| seen.name == task.name) => task,
+                _ => continue,
+            };
+            for cmd in task.get_all_task_cmds() {
+                if let CommandTypes::Task(_) = cmd.key {
+                    pending.extend(cmd.value.split_whitespace().next().map(String::from));
                 }
             }
-            None => default,
-        };
+            found.push(task);
+        }
+        return found;
+    }
+    // combines the selected contexts, later ones win
+    pub fn get_context(&self, names: &[String]) -> HashMap<String, String> {
+        let mut values = HashMap::<String, String>::new();
+        for name in names {
+            if let Ok(context) = resolve_context(&self.contexts, name) {
+                values.extend(context);
+            }
+        }
+        return values;
+    }
+    // every context with its inherited values filled in, sorted by name
+    pub fn resolved_contexts(
+        &self,
+    ) -> Result<BTreeMap<String, HashMap<String, String>>, TaskfileError> {
+        let mut resolved = BTreeMap::new();
+        for name in self.contexts.keys() {
+            resolved.insert(name.to_owned(), resolve_context(&self.contexts, name)?);
+        }
+        return Ok(resolved);
+    }
+    pub fn cli_name(&self) -> &str {
+        return self.cli_name.as_deref().unwrap_or("tasker");
+    }
+    // names the CLI and fills in its about, version and author from the Taskfile
+    pub(crate) fn apply_metadata(&self, command: clap::Command) -> clap::Command {
+        let mut command = command
+            .name(self.cli_name().to_string())
+            .bin_name(self.cli_name().to_string());
+        if let Some(project) = &self.project {
+            command = command.about(project.to_owned());
+        }
+        if let Some(version) = &self.version {
+            command = command.version(version.to_owned());
+        }
+        if let Some(author) = &self.author {
+            command = command.author(author.to_owned());
+        }
+        return command;
     }
-    pub fn create_clap_command(&self) -> clap::Command {
+    pub(crate) fn create_clap_command(&self) -> clap::Command {
         let mut task_vector: Vec<clap::Command> = vec![];
         for task in &self.tasks {
             let new_command = task.create_clap_subcommand();
@@ -47,7 +330,7 @@ impl Taskfile {
             .arg_required_else_help(true)
             .allow_missing_positional(true)
             .subcommands(task_vector);
-        return base_command;
+        return self.apply_metadata(base_command);
     }
 }
 
@@ -73,13 +356,140 @@ mod tests {
     #[test]
     fn test_get_context() {
         let taskfile = load_from_string();
-        let context = taskfile.get_context(Some("test".to_string()));
+        let context = taskfile.get_context(&["test".to_string()]);
+        assert_eq!(context.get("test_key"), Some(&"test_value".to_string()));
+    }
+    #[test]
+    fn test_get_context_combined() {
+        let taskfile = load_from_string();
+        let context = taskfile.get_context(&["test".to_string(), "test-child".to_string()]);
+        assert_eq!(context.get("test_key"), Some(&"child_value".to_string()));
+        assert_eq!(context.get("child_key"), Some(&"child".to_string()));
+        let context = taskfile.get_context(&["test-child".to_string(), "test".to_string()]);
+        assert_eq!(context.get("test_key"), Some(&"test_value".to_string()));
+    }
+    #[test]
+    fn test_load_context_files() {
+        let dir = std::env::temp_dir().join(format!("tasker-contexts-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("eu.yaml"), "extends: [test]\nregion: eu-west-1\n").unwrap();
+        std::fs::write(dir.join("notes.txt"), "not a context").unwrap();
+        let mut taskfile = load_from_string();
+        taskfile.load_context_files(&dir).unwrap();
+        assert!(taskfile.validate_contexts().is_ok());
+        let context = taskfile.get_context(&["eu".to_string()]);
+        assert_eq!(context.get("region"), Some(&"eu-west-1".to_string()));
         assert_eq!(context.get("test_key"), Some(&"test_value".to_string()));
+        assert_eq!(taskfile.contexts.len(), 3);
+        let error = taskfile.load_context_files(&dir).unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("context `eu` is defined in the Taskfile"));
+    }
+    #[test]
+    fn test_load_included_tasks() {
+        let dir = std::env::temp_dir().join(format!("tasker-includes-{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("web")).unwrap();
+        std::fs::write(
+            dir.join("web/build.mk"),
+            "OUT ?= dist\nbuild:\n\tmkdir $(OUT)\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("justfile"),
+            "greet name=\"you\":\n    echo {{name}}\n",
+        )
+        .unwrap();
+        let mut taskfile = load_from_string();
+        taskfile.includes = serde_yaml::from_str(
+            "[{from: make, path: web/build.mk, namespace: web}, {from: just}]",
+        )
+        .unwrap();
+        taskfile.load_included_tasks(&dir).unwrap();
+        let build = taskfile.get_task_by_name("web:build").unwrap();
+        assert_eq!(
+            build.commands[0].value,
+            format!(
+                "make --no-print-directory -C {}/web -f build.mk build OUT=\"${{OUT}}\"",
+                dir.display()
+            )
+        );
+        assert_eq!(build.get_command_args()[0].get_default(), Some("dist"));
+        let greet = taskfile.get_task_by_name("greet").unwrap();
+        assert!(greet.commands[0]
+            .value
+            .ends_with("/justfile greet \"${name}\""));
+        taskfile.includes = serde_yaml::from_str("[{from: npm}]").unwrap();
+        let error = taskfile.load_included_tasks(&dir).unwrap_err();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(error.to_string().contains("could not read"));
+    }
+    #[test]
+    fn test_load_provided_tasks() {
+        let dir = std::env::temp_dir().join(format!("tasker-providers-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("Cargo.toml"), "[package]\nname = \"x\"\n").unwrap();
+        std::fs::write(dir.join("go.mod"), "module x\n").unwrap();
+        let mut taskfile = load_from_string();
+        taskfile.tasks[0].name = "cargo:test".to_string();
+        taskfile.providers = vec!["cargo".to_string()];
+        taskfile.load_provided_tasks(&dir).unwrap();
+        let names: Vec<&str> = taskfile
+            .tasks
+            .iter()
+            .map(|task| task.name.as_str())
+            .filter(|name| name.starts_with("cargo:"))
+            .collect();
+        assert_eq!(
+            names,
+            vec!["cargo:test", "cargo:build", "cargo:lint", "cargo:run"]
+        );
+        assert_eq!(
+            taskfile.tasks[0].description.as_deref(),
+            Some("greets a user by name")
+        );
+        assert_eq!(taskfile.tasks.last().unwrap().tags, vec!["provided"]);
+        taskfile.providers = vec!["gradle".to_string()];
+        let error = taskfile.load_provided_tasks(&dir).unwrap_err();
+        let _ = std::fs::remove_dir_all(&dir);
+        assert!(error
+            .to_string()
+            .contains("provider `gradle` does not exist"));
+    }
+    #[test]
+    fn test_validate_shells() {
+        let mut taskfile = load_from_string();
+        assert!(taskfile.validate_shells().is_ok());
+        taskfile.tasks[0].shell = Some(serde_yaml::from_str("not-a-shell -c").unwrap());
+        let error = taskfile.validate_shells().unwrap_err();
+        assert!(error
+            .to_string()
+            .contains("shell `not-a-shell` for task `test-cmd` was not found on PATH"));
+    }
+    #[test]
+    fn test_validate_context_references() {
+        let mut taskfile = load_from_string();
+        assert!(taskfile.validate_contexts().is_ok());
+        taskfile.default_context = Some("prdo".to_string());
+        taskfile.tasks[0].contexts = vec!["test".to_string(), "nope".to_string()];
+        let error = taskfile.validate_contexts().unwrap_err().to_string();
+        assert!(error.contains("default_context names context `prdo`, which does not exist"));
+        assert!(error.contains("task `test-cmd` names context `nope`, which does not exist"));
+    }
+    #[test]
+    fn test_reachable_tasks() {
+        let taskfile = load_from_string();
+        let names: Vec<&str> = taskfile
+            .reachable_tasks("test-task")
+            .iter()
+            .map(|task| task.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["test-task", "test-cmd"]);
     }
     #[test]
     fn test_get_context_none() {
         let taskfile = load_from_string();
-        let context = taskfile.get_context(None);
+        let context = taskfile.get_context(&[]);
         assert_eq!(context.get("test"), None);
     }
 }
@@ -1,5 +1,9 @@
-use super::cmd::{CmdArg, TaskCmd};
+use super::{
+    cmd::{CmdArg, TaskCmd},
+    settings::{HumanDuration, RetryPolicy, RunMode, Shell},
+};
 use serde::Deserialize;
+use std::path::PathBuf;
 
 // task file command is a single defined command stanza from a config
 #[derive(Deserialize, Clone)]
@@ -10,6 +14,40 @@ pub struct TaskStanza {
     #[serde(rename(deserialize = "args"))]
     command_args: Vec<CmdArg>,
     pub description: Option<String>,
+    // overrides the Taskfile level mode
+    #[serde(default)]
+    pub mode: Option<RunMode>,
+    // run when any command in the task fails
+    #[serde(default, alias = "on-fail")]
+    pub on_fail: Vec<TaskCmd>,
+    // always run once the task is done, even on failure or Ctrl-C
+    #[serde(default, alias = "defer")]
+    pub finally: Vec<TaskCmd>,
+    // defaults for every command of the task that does not set its own
+    #[serde(default)]
+    pub retries: Option<RetryPolicy>,
+    #[serde(default)]
+    pub timeout: Option<HumanDuration>,
+    #[serde(default)]
+    pub interactive: bool,
+    // overrides the Taskfile level shell
+    #[serde(default)]
+    pub shell: Option<Shell>,
+    // overrides the Taskfile level strict setting
+    #[serde(default)]
+    pub strict: Option<bool>,
+    // contexts the task may run under, empty allows any
+    #[serde(default)]
+    pub contexts: Vec<String>,
+    // question that has to be answered with yes before the task runs
+    #[serde(default)]
+    pub confirm: Option<String>,
+    // free form labels for grouping tasks in listings
+    #[serde(default)]
+    pub tags: Vec<String>,
+    // the file the task was defined in, set on load
+    #[serde(skip)]
+    pub source: Option<PathBuf>,
 }
 
 impl TaskStanza {
@@ -24,9 +62,50 @@ impl TaskStanza {
         return base_command;
     }
 
+    // a task tasker puts together itself rather than reading it from the Taskfile, like the
+    // ones providers and includes contribute
+    pub(super) fn generated(
+        name: String,
+        description: String,
+        commands: Vec<TaskCmd>,
+        command_args: Vec<CmdArg>,
+        tag: &str,
+        source: PathBuf,
+    ) -> TaskStanza {
+        return TaskStanza {
+            name,
+            commands,
+            command_args,
+            description: Some(description),
+            mode: None,
+            on_fail: vec![],
+            finally: vec![],
+            retries: None,
+            timeout: None,
+            interactive: false,
+            shell: None,
+            strict: None,
+            contexts: vec![],
+            confirm: None,
+            tags: vec![tag.to_string()],
+            source: Some(source),
+        };
+    }
+    // `docker:build` lives in the `docker` namespace
+    pub fn namespace(&self) -> Option<&str> {
+        return self.name.rsplit_once(':').map(|(namespace, _)| namespace);
+    }
     pub fn get_command_args(&self) -> &Vec<CmdArg> {
         &self.command_args
     }
+    // commands along with the on_fail and finally handlers
+    pub fn get_all_task_cmds(&self) -> Vec<&TaskCmd> {
+        self.commands
+            .iter()
+            .chain(self.on_fail.iter())
+            .chain(self.finally.iter())
+            .collect()
+    }
 }
 
 #[cfg(test)]
@@ -41,11 +120,17 @@ mod tests {
             name: "arg1".to_string(),
             default: Some("default".to_string()),
             arg_type: "string".to_string(),
+            prompt: None,
+            choices: vec![],
+            secret: false,
         };
         let arg2 = CmdArg {
             name: "arg2".to_string(),
             default: None,
             arg_type: "string".to_string(),
+            prompt: None,
+            choices: vec![],
+            secret: false,
         };
         arg_vector.push(arg1);
         arg_vector.push(arg2);
@@ -54,9 +139,26 @@ mod tests {
             commands: vec![TaskCmd {
                 key: CommandTypes::Task("test".to_string()),
                 value: "test".to_string(),
+                ignore_error: false,
+                retries: None,
+                timeout: None,
+                interactive: false,
+                interpreter: None,
             }],
             command_args: arg_vector,
             description: None,
+            mode: None,
+            on_fail: vec![],
+            finally: vec![],
+            retries: None,
+            timeout: None,
+            interactive: false,
+            shell: None,
+            strict: None,
+            contexts: vec![],
+            confirm: None,
+            tags: vec![],
+            source: None,
         };
         let subcommand = task_stanza.create_clap_subcommand();
         let mut args = subcommand.get_arguments();
@@ -67,6 +169,24 @@ mod tests {
         assert_eq!(arg_1.get_default_values(), &["default"]);
         let arg_2 = args.next().unwrap();
         assert_eq!(arg_2.get_id(), "arg2");
-        assert_eq!(arg_2.get_default_values().is_empty(), true);
+        assert!(arg_2.get_default_values().is_empty());
+    }
+
+    #[test]
+    fn test_deserialize_handlers() {
+        let yaml = r#"
+        name: test
+        commands:
+          - shell: "false"
+        on-fail:
+          - shell: echo failed
+        defer:
+          - shell: echo cleanup
+        args:
+        "#;
+        let task_stanza: TaskStanza = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(task_stanza.on_fail[0].value, "echo failed");
+        assert_eq!(task_stanza.finally[0].value, "echo cleanup");
+        assert!(task_stanza.mode.is_none());
     }
 }
@@ -37,6 +37,9 @@ pub enum UserFacingError {
     TaskExecutionError(ErrWithMessage),   // Command in task failed to run
     MissingArgError(ErrWithMessage),      // Missing argument
     TaskDoesNotExist(ErrWithMessage),     // Task does not exist
+    Interrupted(ErrWithMessage, i32),     // Stopped by a signal
+    InvalidContext(ErrWithMessage),       // Unknown or disallowed context
+    Aborted(ErrWithMessage),              // Confirmation declined or impossible
 }
 
 impl std::error::Error for UserFacingError {}
@@ -44,11 +47,23 @@ impl std::error::Error for UserFacingError {}
 impl fmt::Display for UserFacingError {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            UserFacingError::TaskfileDoesNotExist(e) => write!(f, "{}", e.to_string()),
-            UserFacingError::TaskfileParseError(e) => write!(f, "{}", e.to_string()),
-            UserFacingError::TaskExecutionError(e) => write!(f, "{}", e.to_string()),
-            UserFacingError::MissingArgError(e) => write!(f, "{}", e.to_string()),
-            UserFacingError::TaskDoesNotExist(e) => write!(f, "{}", e.to_string()),
+            UserFacingError::TaskfileDoesNotExist(e) => write!(f, "{}", e),
+            UserFacingError::TaskfileParseError(e) => write!(f, "{}", e),
+            UserFacingError::TaskExecutionError(e) => write!(f, "{}", e),
+            UserFacingError::MissingArgError(e) => write!(f, "{}", e),
+            UserFacingError::TaskDoesNotExist(e) => write!(f, "{}", e),
+            UserFacingError::Interrupted(e, _) => write!(f, "{}", e),
+            UserFacingError::InvalidContext(e) => write!(f, "{}", e),
+            UserFacingError::Aborted(e) => write!(f, "{}", e),
+        }
+    }
+}
+impl UserFacingError {
+    // shells report death by signal as 128 + the signal number, e.g. 130 for Ctrl-C
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            UserFacingError::Interrupted(_, signal) => 128 + signal,
+            _ => 1,
         }
     }
 }
@@ -63,6 +78,10 @@ impl From<TaskfileError> for UserFacingError {
                 e.add_to_stack("Taskfile encountered parsing issue".to_string());
                 UserFacingError::TaskfileParseError(e)
             }
+            TaskfileError::InvalidSetting(mut e) => {
+                e.add_to_stack("Taskfile has an invalid setting".to_string());
+                UserFacingError::TaskfileParseError(e)
+            }
         }
     }
 }
@@ -73,6 +92,21 @@ impl From<ExecutionError> for UserFacingError {
                 e.add_to_stack("Command failed to execute".to_string());
                 UserFacingError::TaskExecutionError(e)
             }
+            ExecutionError::StepFailed(failure) => {
+                let mut e = failure.to_err_with_message();
+                e.add_to_stack("Command failed to execute".to_string());
+                UserFacingError::TaskExecutionError(e)
+            }
+            ExecutionError::TimedOut(timeout) => {
+                let mut e = timeout.to_err_with_message();
+                e.add_to_stack("Command timed out".to_string());
+                UserFacingError::TaskExecutionError(e)
+            }
+            ExecutionError::Interrupted(signal) => {
+                let mut e = ExecutionError::interrupted_message(signal);
+                e.add_to_stack("Execution interrupted".to_string());
+                UserFacingError::Interrupted(e, signal)
+            }
         }
     }
 }
@@ -110,6 +144,22 @@ mod test {
         }
     }
 
+    #[test]
+    fn test_exit_code() {
+        let error = UserFacingError::from(crate::run::errors::ExecutionError::Interrupted(
+            libc::SIGINT,
+        ));
+        assert_eq!(error.exit_code(), 130);
+        let error = UserFacingError::from(crate::run::errors::ExecutionError::Interrupted(
+            libc::SIGTERM,
+        ));
+        assert_eq!(error.exit_code(), 143);
+        let error = UserFacingError::MissingArgError(ErrWithMessage {
+            code: "MISSING_ARGUMENT".to_string(),
+            messages: vec!["missing".to_string()],
+        });
+        assert_eq!(error.exit_code(), 1);
+    }
     #[test]
     fn test_display_user_facing_error() {
         let error = UserFacingError::TaskfileDoesNotExist(ErrWithMessage {
@@ -1,12 +1,19 @@
-// TODO make this a crate
 pub mod errors;
 pub mod iters;
+pub mod paths;
+pub mod prompt;
+pub mod secrets;
 pub mod strings;
 pub mod test_helpers;
 
 #[cfg(test)]
 mod unittest {
-    use crate::utils::strings::split_exclude_quotes;
+    use crate::utils::paths::find_executable;
+    use crate::utils::secrets::Secrets;
+    use crate::utils::strings::{
+        closest_match, edit_distance, parse_duration, split_exclude_quotes, yaml_string,
+    };
+    use std::time::Duration;
 
     #[test]
     fn test_split_exclude_quotes() {
@@ -18,4 +25,71 @@ mod unittest {
         let spl = split_exclude_quotes("echo \"beginning is here\" \"end is here\"".to_string());
         assert_eq!(vec!["echo", "beginning is here", "end is here"], spl);
     }
+    #[test]
+    fn test_yaml_string() {
+        assert_eq!(yaml_string("cargo build"), "cargo build");
+        assert_eq!(yaml_string("docker:build"), "docker:build");
+        assert_eq!(yaml_string("${CC} -o $@"), "${CC} -o $@");
+        assert_eq!(yaml_string("a: b"), "\"a: b\"");
+        assert_eq!(yaml_string("8080"), "\"8080\"");
+        assert_eq!(yaml_string("`x` y"), "\"`x` y\"");
+        assert_eq!(yaml_string(""), "\"\"");
+    }
+    #[test]
+    fn test_parse_duration() {
+        assert_eq!(parse_duration("90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("5m").unwrap(), Duration::from_secs(300));
+        assert_eq!(parse_duration("1h30m").unwrap(), Duration::from_secs(5400));
+        assert_eq!(parse_duration("250ms").unwrap(), Duration::from_millis(250));
+        assert_eq!(parse_duration("2").unwrap(), Duration::from_secs(2));
+    }
+    #[test]
+    fn test_parse_duration_invalid() {
+        assert!(parse_duration("").is_err());
+        assert!(parse_duration("5 minutes").is_err());
+        assert!(parse_duration("10x").is_err());
+        assert!(parse_duration("m").is_err());
+        assert!(parse_duration("-5").is_err());
+    }
+    #[test]
+    fn test_parse_duration_out_of_range() {
+        assert!(parse_duration("inf").is_err());
+        assert!(parse_duration("nan").is_err());
+        assert!(parse_duration("1e400").is_err());
+        assert!(parse_duration("99999999999999999999h").is_err());
+        assert!(parse_duration("18446744073709549568s1000000h").is_err());
+    }
+    #[test]
+    fn test_closest_match() {
+        assert_eq!(edit_distance("prod", "prd"), 1);
+        assert_eq!(edit_distance("staging", "stage"), 3);
+        let candidates = ["prod", "staging"];
+        assert_eq!(closest_match("prd", &candidates), Some("prod"));
+        assert_eq!(closest_match("stagign", &candidates), Some("staging"));
+        assert_eq!(closest_match("local", &candidates), None);
+    }
+    #[test]
+    fn test_mask_secrets() {
+        let mut secrets = Secrets::default();
+        assert!(secrets.is_empty());
+        secrets.register("s3cr3t");
+        secrets.register("s3cr3t-token");
+        secrets.register("");
+        assert!(!secrets.is_empty());
+        assert_eq!(
+            secrets.mask("curl -H s3cr3t-token && echo s3cr3t"),
+            "curl -H *** && echo ***"
+        );
+        assert_eq!(secrets.mask("nothing here"), "nothing here");
+        assert_eq!(format!("{:?}", secrets), "Secrets(2 values)");
+        // secrets of one run are not masked in another
+        assert_eq!(Secrets::default().mask("s3cr3t"), "s3cr3t");
+    }
+    #[test]
+    fn test_find_executable() {
+        assert!(find_executable("sh").is_some());
+        assert!(find_executable("/bin/sh").is_some());
+        assert!(find_executable("definitely-not-a-real-shell").is_none());
+        assert!(find_executable("/etc/hostname-not-here").is_none());
+    }
 }
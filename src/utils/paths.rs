@@ -0,0 +1,17 @@
+use std::{os::unix::fs::PermissionsExt, path::PathBuf};
+
+// looks a program up the way a shell would, paths are used as is and names are searched on PATH
+pub fn find_executable(program: &str) -> Option<PathBuf> {
+    let is_executable = |path: &PathBuf| match std::fs::metadata(path) {
+        Ok(metadata) => metadata.is_file() && metadata.permissions().mode() & 0o111 != 0,
+        Err(_) => false,
+    };
+    if program.contains('/') {
+        let path = PathBuf::from(shellexpand::tilde(program).to_string());
+        return Some(path).filter(is_executable);
+    }
+    let search_path = std::env::var_os("PATH")?;
+    std::env::split_paths(&search_path)
+        .map(|dir| dir.join(program))
+        .find(is_executable)
+}
@@ -0,0 +1,37 @@
+use std::io::{self, BufRead, IsTerminal, Write};
+
+// prompts only make sense when someone is at the keyboard
+pub fn is_interactive() -> bool {
+    io::stdin().is_terminal()
+}
+
+// asks on stderr so prompts never end up in piped output
+pub fn read_line(prompt: &str) -> io::Result<String> {
+    eprint!("{}", prompt);
+    io::stderr().flush()?;
+    let mut answer = String::new();
+    if io::stdin().lock().read_line(&mut answer)? == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::UnexpectedEof,
+            "stdin closed before an answer was given",
+        ));
+    }
+    Ok(answer.trim_end_matches(['\n', '\r']).to_string())
+}
+
+// like read_line, with terminal echo turned off while typing
+pub fn read_secret(prompt: &str) -> io::Result<String> {
+    let fd = libc::STDIN_FILENO;
+    let mut termios = unsafe { std::mem::zeroed::<libc::termios>() };
+    if unsafe { libc::tcgetattr(fd, &mut termios) } != 0 {
+        return Err(io::Error::last_os_error());
+    }
+    let original = termios;
+    termios.c_lflag &= !libc::ECHO;
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &termios) };
+    let answer = read_line(prompt);
+    unsafe { libc::tcsetattr(fd, libc::TCSANOW, &original) };
+    // the newline typed by the user was not echoed either
+    eprintln!();
+    answer
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+// values that are replaced with *** in anything tasker prints about a run
+#[derive(Clone, Default, PartialEq)]
+pub struct Secrets {
+    values: Vec<String>,
+}
+impl Secrets {
+    pub fn register(&mut self, value: &str) {
+        if value.is_empty() || self.values.iter().any(|secret| secret == value) {
+            return;
+        }
+        self.values.push(value.to_string());
+    }
+    pub fn extend(&mut self, other: &Secrets) {
+        for value in &other.values {
+            self.register(value);
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+    pub fn mask(&self, text: &str) -> String {
+        // longer secrets first so one containing another is masked whole
+        let mut secrets: Vec<&String> = self.values.iter().collect();
+        secrets.sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        let mut masked = text.to_string();
+        for secret in secrets {
+            masked = masked.replace(secret.as_str(), "***");
+        }
+        masked
+    }
+}
+// plans and runners get printed with `{:?}`, which must not show the values either
+impl fmt::Debug for Secrets {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Secrets({} values)", self.values.len())
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 pub fn split_exclude_quotes(s: String) -> Vec<String> {
     let mut split = vec![];
     let mut current = String::new();
@@ -15,3 +17,100 @@ pub fn split_exclude_quotes(s: String) -> Vec<String> {
     split.push(current.clone().to_string());
     split
 }
+
+// a string as a YAML scalar, bare when YAML reads it back as the same string
+pub fn yaml_string(value: &str) -> String {
+    let plain = !value.contains(['\n', '"', '\''])
+        && serde_yaml::from_str::<serde_yaml::Value>(value).ok()
+            == Some(serde_yaml::Value::String(value.to_string()));
+    return match plain {
+        true => value.to_string(),
+        // JSON strings are valid YAML double quoted strings
+        false => serde_json::to_string(value).unwrap(),
+    };
+}
+
+// a number of seconds as a duration, rejecting what does not fit one
+fn seconds_duration(seconds: f64, s: &str) -> Result<Duration, String> {
+    if seconds < 0.0 {
+        return Err(format!("duration `{}` can not be negative", s));
+    }
+    match Duration::try_from_secs_f64(seconds) {
+        Ok(duration) => Ok(duration),
+        Err(_) => Err(format!("duration `{}` is not a finite length of time", s)),
+    }
+}
+
+// parses durations like `90s`, `5m`, `1h30m` or `250ms`, a bare number counts as seconds
+pub fn parse_duration(s: &str) -> Result<Duration, String> {
+    let s = s.trim();
+    if s.is_empty() {
+        return Err("duration can not be empty".to_string());
+    }
+    if let Ok(seconds) = s.parse::<f64>() {
+        return seconds_duration(seconds, s);
+    }
+    let mut total = Duration::ZERO;
+    let mut number = String::new();
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+        let mut unit = c.to_string();
+        while let Some(next) = chars.peek().filter(|next| next.is_ascii_alphabetic()) {
+            unit.push(*next);
+            chars.next();
+        }
+        let value: f64 = match number.parse() {
+            Ok(value) => value,
+            Err(_) => return Err(format!("invalid duration `{}`", s)),
+        };
+        let seconds = match unit.as_str() {
+            "ms" => value / 1000.0,
+            "s" => value,
+            "m" => value * 60.0,
+            "h" => value * 3600.0,
+            _ => return Err(format!("unknown unit `{}` in duration `{}`", unit, s)),
+        };
+        total = match total.checked_add(seconds_duration(seconds, s)?) {
+            Some(total) => total,
+            None => return Err(format!("duration `{}` is too long", s)),
+        };
+        number.clear();
+    }
+    if !number.is_empty() {
+        return Err(format!(
+            "invalid duration `{}`, expected something like 90s or 5m",
+            s
+        ));
+    }
+    Ok(total)
+}
+
+// number of single character edits between two strings
+pub fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(ca != *cb);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+// the candidate a typo most likely meant, if any is close enough
+pub fn closest_match<'a>(name: &str, candidates: &[&'a str]) -> Option<&'a str> {
+    let max_distance = (name.chars().count() / 3).max(2);
+    candidates
+        .iter()
+        .map(|candidate| (edit_distance(name, candidate), *candidate))
+        .filter(|(distance, _)| *distance <= max_distance)
+        .min()
+        .map(|(_, candidate)| candidate)
+}
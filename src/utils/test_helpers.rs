@@ -8,6 +8,10 @@ author: "Peter"
 contexts:
   test:
     test_key: test_value
+  test-child:
+    extends: [test]
+    test_key: child_value
+    child_key: child
 tasks:
   - name: test-cmd
     commands:
@@ -25,6 +29,29 @@ tasks:
     - task: test-cmd Bar Foo
     description: "greets a user by name"
     args:
+  - name: test-cleanup
+    retries: 2
+    timeout: 30s
+    commands:
+    - shell: "false"
+      ignore_error: true
+    on_fail:
+    - task: test-cmd Foo
+    finally:
+    - shell: echo cleanup
+    args:
+  - name: test-shells
+    commands:
+    - shell: echo default
+    - shell: print("python")
+      interpreter: python3 -c
+    - task: test-sh
+    args:
+  - name: test-sh
+    shell: sh -c
+    commands:
+    - shell: echo sh
+    args:
 "#;
         return serde_yaml::from_str(example_file).unwrap();
     }